@@ -0,0 +1,249 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Rect;
+use crate::Get;
+use crate::indexed::*;
+
+// How much red, green and blue contribute to the brightness of a gray pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LumaWeights {
+	// ITU-R BT.601, the classic television weights
+	Rec601,
+	// ITU-R BT.709, matching sRGB primaries
+	Rec709,
+	// Plain average of the three channels
+	Average,
+}
+
+impl LumaWeights {
+	pub fn weights(&self) -> [f64; 3] {
+		match self {
+			LumaWeights::Rec601 => [0.299, 0.587, 0.114],
+			LumaWeights::Rec709 => [0.2126, 0.7152, 0.0722],
+			LumaWeights::Average => [1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0],
+		}
+	}
+
+	pub fn luma(&self, color: &Rgb) -> f64 {
+		let weights = self.weights();
+		weights[0] * color.r as f64 + weights[1] * color.g as f64 + weights[2] * color.b as f64
+	}
+}
+
+fn to_u8(value: f64) -> u8 {
+	value.round().clamp(0.0, 255.0) as u8
+}
+
+// Builds a lookup table by evaluating f once for every channel value
+fn build_lut<F: Fn(f64) -> f64>(f: F) -> [u8; 256] {
+	let mut lut = [0; 256];
+	for (value, entry) in lut.iter_mut().enumerate() {
+		*entry = to_u8(f(value as f64));
+	}
+	lut
+}
+
+// Multiplies a color by a 3x3 matrix acting on [r, g, b]
+fn apply_matrix(color: Rgb, matrix: &[[f64; 3]; 3]) -> Rgb {
+	let input = [color.r as f64, color.g as f64, color.b as f64];
+	let row = |i: usize| matrix[i][0] * input[0] + matrix[i][1] * input[1] + matrix[i][2] * input[2];
+	Rgb { r: to_u8(row(0)), g: to_u8(row(1)), b: to_u8(row(2)) }
+}
+
+pub trait Adjust {
+	fn map_colors<F: FnMut(Rgb) -> Rgb>(&mut self, region: Option<&Rect>, f: F);
+	fn apply_lut(&mut self, lut: &[u8; 256], region: Option<&Rect>);
+	fn adjust_brightness_contrast(&mut self, brightness: i32, contrast: f64, region: Option<&Rect>);
+	fn adjust_gamma(&mut self, gamma: f64, region: Option<&Rect>);
+	fn adjust_saturation(&mut self, factor: f64, region: Option<&Rect>);
+	fn rotate_hue(&mut self, degrees: f64, region: Option<&Rect>);
+	fn invert(&mut self, region: Option<&Rect>);
+	fn sepia(&mut self, region: Option<&Rect>);
+	fn grayscale(&mut self, weights: LumaWeights, region: Option<&Rect>);
+}
+
+impl Adjust for Vec<u8> {
+	// Replaces every pixel inside region (the whole image for None) with f of its color.
+	// Indexed images adjusted as a whole have their color table mapped instead, so the result is not limited to the old palette.
+	// 24 and 32 bit images are edited directly in the pixel array, other depths go through get_pixel and draw_point.
+	fn map_colors<F: FnMut(Rgb) -> Rgb>(&mut self, region: Option<&Rect>, mut f: F) {
+		if region.is_none() && self.is_indexed() {
+			let palette: Vec<Rgb> = self.get_palette().into_iter().map(f).collect();
+			let _ = self.set_palette(&palette);
+			return;
+		}
+		let bounds = self.clip_rect();
+		let area = match region {
+			Some(rect) => rect.intersect(&bounds),
+			None => bounds,
+		};
+		if area.is_empty() {
+			return;
+		}
+		let bits_per_pixel = self.get_bits_per_pixel();
+		if bits_per_pixel == 24 || bits_per_pixel == 32 {
+			let bytes_per_pixel = bits_per_pixel as usize / 8;
+			let row_size = self.get_row_size();
+			let pixel_array = self.get_pixel_array_offset();
+			// A header claiming more pixels than the buffer holds leaves the image alone
+			let end = pixel_array + (area.y + area.height - 1) as usize * row_size + (area.x + area.width) as usize * bytes_per_pixel;
+			if end > self.len() {
+				return;
+			}
+			for y in area.y..area.y + area.height {
+				let row_start = pixel_array + y as usize * row_size;
+				for x in area.x..area.x + area.width {
+					let offset = row_start + x as usize * bytes_per_pixel;
+					let color = f(Rgb { r: self[offset + 2], g: self[offset + 1], b: self[offset] });
+					self[offset] = color.b;
+					self[offset + 1] = color.g;
+					self[offset + 2] = color.r;
+				}
+			}
+		} else {
+			for y in area.y..area.y + area.height {
+				for x in area.x..area.x + area.width {
+					let point = Point { x, y };
+					if let Ok(color) = self.get_pixel(&point) {
+						self.draw_point(&point, &f(color));
+					}
+				}
+			}
+		}
+	}
+
+	// Passes every channel through the same 256 entry table
+	fn apply_lut(&mut self, lut: &[u8; 256], region: Option<&Rect>) {
+		self.map_colors(region, |color| Rgb { r: lut[color.r as usize], g: lut[color.g as usize], b: lut[color.b as usize] });
+	}
+
+	// Brightness is added to every channel. Contrast scales the distance from mid gray, 1.0 leaves it unchanged.
+	fn adjust_brightness_contrast(&mut self, brightness: i32, contrast: f64, region: Option<&Rect>) {
+		let lut = build_lut(|value| (value - 128.0) * contrast + 128.0 + brightness as f64);
+		self.apply_lut(&lut, region);
+	}
+
+	// Values above 1.0 brighten the mid tones, values below darken them. Non-positive gamma leaves the image unchanged.
+	fn adjust_gamma(&mut self, gamma: f64, region: Option<&Rect>) {
+		if gamma <= 0.0 {
+			return;
+		}
+		let lut = build_lut(|value| 255.0 * (value / 255.0).powf(1.0 / gamma));
+		self.apply_lut(&lut, region);
+	}
+
+	// 0.0 turns the image gray, 1.0 leaves it unchanged and larger values make colors more vivid
+	fn adjust_saturation(&mut self, factor: f64, region: Option<&Rect>) {
+		let [wr, wg, wb] = LumaWeights::Rec709.weights();
+		let matrix = [
+			[wr + (1.0 - wr) * factor, wg - wg * factor, wb - wb * factor],
+			[wr - wr * factor, wg + (1.0 - wg) * factor, wb - wb * factor],
+			[wr - wr * factor, wg - wg * factor, wb + (1.0 - wb) * factor],
+		];
+		self.map_colors(region, |color| apply_matrix(color, &matrix));
+	}
+
+	// Rotates every color around the gray axis while keeping its luma, the same matrix as the CSS hue-rotate filter
+	fn rotate_hue(&mut self, degrees: f64, region: Option<&Rect>) {
+		let (sin, cos) = degrees.to_radians().sin_cos();
+		let matrix = [
+			[0.213 + cos * 0.787 - sin * 0.213, 0.715 - cos * 0.715 - sin * 0.715, 0.072 - cos * 0.072 + sin * 0.928],
+			[0.213 - cos * 0.213 + sin * 0.143, 0.715 + cos * 0.285 + sin * 0.140, 0.072 - cos * 0.072 - sin * 0.283],
+			[0.213 - cos * 0.213 - sin * 0.787, 0.715 - cos * 0.715 + sin * 0.715, 0.072 + cos * 0.928 + sin * 0.072],
+		];
+		self.map_colors(region, |color| apply_matrix(color, &matrix));
+	}
+
+	fn invert(&mut self, region: Option<&Rect>) {
+		let lut = build_lut(|value| 255.0 - value);
+		self.apply_lut(&lut, region);
+	}
+
+	fn sepia(&mut self, region: Option<&Rect>) {
+		let matrix = [
+			[0.393, 0.769, 0.189],
+			[0.349, 0.686, 0.168],
+			[0.272, 0.534, 0.131],
+		];
+		self.map_colors(region, |color| apply_matrix(color, &matrix));
+	}
+
+	fn grayscale(&mut self, weights: LumaWeights, region: Option<&Rect>) {
+		self.map_colors(region, |color| {
+			let luma = to_u8(weights.luma(&color));
+			Rgb { r: luma, g: luma, b: luma }
+		});
+	}
+}
+
+#[test]
+fn test_lookup_table_adjustments() {
+	let color = Rgb {r: 200, g: 100, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(4, 3, 24);
+	bmp.map_colors(None, |_| color);
+
+	let original = bmp.clone();
+	bmp.invert(Some(&Rect {x: 2, y: 0, width: 10, height: 1}));
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 0}).unwrap(), Rgb {r: 55, g: 155, b: 255});
+	assert_eq!(bmp.get_pixel(&Point {x: 1, y: 0}).unwrap(), color);
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 1}).unwrap(), color);
+	bmp.invert(Some(&Rect {x: 2, y: 0, width: 10, height: 1}));
+	assert_eq!(bmp, original);
+
+	bmp.adjust_gamma(1.0, None);
+	bmp.adjust_brightness_contrast(0, 1.0, None);
+	assert_eq!(bmp, original);
+	bmp.adjust_brightness_contrast(100, 1.0, None);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 255, g: 200, b: 100});
+	bmp.adjust_brightness_contrast(0, 0.0, None);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 128, g: 128, b: 128});
+}
+
+#[test]
+fn test_color_matrix_adjustments() {
+	let color = Rgb {r: 200, g: 100, b: 50};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(2, 2, 32);
+	bmp.map_colors(None, |_| color);
+
+	let mut gray = bmp.clone();
+	gray.adjust_saturation(0.0, None);
+	let mut luma = bmp.clone();
+	luma.grayscale(LumaWeights::Rec709, None);
+	assert_eq!(gray, luma);
+	assert_eq!(luma.get_pixel(&Point {x: 1, y: 1}).unwrap(), Rgb {r: 118, g: 118, b: 118});
+
+	let mut unchanged = bmp.clone();
+	unchanged.adjust_saturation(1.0, None);
+	unchanged.rotate_hue(360.0, None);
+	assert_eq!(unchanged, bmp);
+
+	// A half turn of hue moves orange towards blue
+	let mut rotated = bmp.clone();
+	rotated.rotate_hue(180.0, None);
+	let turned = rotated.get_pixel(&Point {x: 0, y: 0}).unwrap();
+	assert!(turned.b > turned.r);
+
+	bmp.sepia(None);
+	let toned = bmp.get_pixel(&Point {x: 0, y: 0}).unwrap();
+	assert!(toned.r > toned.g && toned.g > toned.b);
+}
+
+#[test]
+fn test_indexed_and_truncated_adjustments() {
+	let palette = [Rgb {r: 0, g: 0, b: 128}, Rgb {r: 255, g: 200, b: 0}];
+	let mut bmp = Vec::<u8>::new_indexed_bitmap(3, 2, 1, &palette).unwrap();
+	bmp.set_pixel_index(&Point {x: 2, y: 1}, 1);
+
+	// The whole image maps the color table, keeping the pixel indices
+	bmp.invert(None);
+	assert_eq!(bmp.get_palette(), vec![Rgb {r: 255, g: 255, b: 127}, Rgb {r: 0, g: 55, b: 255}]);
+	assert_eq!(bmp.get_pixel(&Point {x: 2, y: 1}).unwrap(), Rgb {r: 0, g: 55, b: 255});
+
+	// A header overstating the image size is ignored instead of panicking
+	let mut truncated:Vec<u8> = Vec::<u8>::new_bitmap(4, 4, 24);
+	truncated.truncate(truncated.len() - 10);
+	let before = truncated.clone();
+	truncated.invert(None);
+	assert_eq!(truncated, before);
+}
@@ -0,0 +1,180 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Rect;
+use crate::Get;
+
+// How a drawn color is combined with the pixel already in the bitmap.
+// The destination is always treated as opaque, since the pixel array stores no alpha.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+	// Separable blend modes, composited over the destination with the source opacity
+	SourceOver,
+	Multiply,
+	Screen,
+	Overlay,
+	Darken,
+	Lighten,
+	Additive,
+	// Bitwise XOR of the channel values, as used for rubber band selections
+	Xor,
+	// Porter-Duff operators
+	Clear,
+	Source,
+	Destination,
+	DestinationOver,
+	SourceIn,
+	DestinationIn,
+	SourceOut,
+	DestinationOut,
+	SourceAtop,
+	DestinationAtop,
+	PorterDuffXor,
+}
+
+// Mixes one channel of the source (cs) and destination (cb), both in 0..1
+fn blend_channel(cs: f64, cb: f64, mode: BlendMode) -> f64 {
+	match mode {
+		BlendMode::Multiply => cs * cb,
+		BlendMode::Screen => cs + cb - cs * cb,
+		BlendMode::Overlay => {
+			if cb <= 0.5 {
+				2.0 * cs * cb
+			} else {
+				let cb = 2.0 * cb - 1.0;
+				cs + cb - cs * cb
+			}
+		}
+		BlendMode::Darken => cs.min(cb),
+		BlendMode::Lighten => cs.max(cb),
+		BlendMode::Additive => (cs + cb).min(1.0),
+		BlendMode::Xor => ((cs * 255.0).round() as u8 ^ (cb * 255.0).round() as u8) as f64 / 255.0,
+		_ => cs,
+	}
+}
+
+// Porter-Duff fractions (Fa, Fb) for a source with alpha as over an opaque destination
+fn porter_duff_factors(alpha: f64, mode: BlendMode) -> (f64, f64) {
+	match mode {
+		BlendMode::Clear => (0.0, 0.0),
+		BlendMode::Source => (1.0, 0.0),
+		BlendMode::Destination => (0.0, 1.0),
+		BlendMode::DestinationOver => (0.0, 1.0),
+		BlendMode::SourceIn => (1.0, 0.0),
+		BlendMode::DestinationIn => (0.0, alpha),
+		BlendMode::SourceOut => (0.0, 0.0),
+		BlendMode::DestinationOut => (0.0, 1.0 - alpha),
+		BlendMode::SourceAtop => (1.0, 1.0 - alpha),
+		BlendMode::DestinationAtop => (0.0, alpha),
+		BlendMode::PorterDuffXor => (0.0, 1.0 - alpha),
+		// Every separable mode is composited with source-over
+		_ => (1.0, 1.0 - alpha),
+	}
+}
+
+// Combines source, drawn with the given opacity (0..1), with the destination pixel.
+// The result is flattened to an opaque color, which is what a bitmap without alpha can store.
+pub fn composite(source: &Rgb, destination: &Rgb, opacity: f64, mode: BlendMode) -> Rgb {
+	let alpha = opacity.clamp(0.0, 1.0);
+	let (fa, fb) = porter_duff_factors(alpha, mode);
+	let channel = |cs: u8, cd: u8| -> u8 {
+		let cs = cs as f64 / 255.0;
+		let cd = cd as f64 / 255.0;
+		let mixed = blend_channel(cs, cd, mode);
+		let result = alpha * fa * mixed + fb * cd;
+		(result.clamp(0.0, 1.0) * 255.0).round() as u8
+	};
+	Rgb {
+		r: channel(source.r, destination.r),
+		g: channel(source.g, destination.g),
+		b: channel(source.b, destination.b),
+	}
+}
+
+// Draws color onto the pixel at point of bitmap with the given opacity and blend mode
+pub(crate) fn composite_point(bitmap: &mut Vec<u8>, point: &Point, color: &Rgb, opacity: f64, blend_mode: BlendMode) {
+	// Opaque source-over is a plain overwrite, so the destination only needs reading otherwise
+	if blend_mode == BlendMode::SourceOver && opacity >= 1.0 {
+		bitmap.draw_point(point, color);
+		return;
+	}
+	if let Ok(destination) = bitmap.get_pixel(point) {
+		bitmap.draw_point(point, &composite(color, &destination, opacity, blend_mode));
+	}
+}
+
+// A bitmap borrowed for a single drawing call, compositing everything drawn with an opacity and blend mode.
+// Blended implements Bitmap, so every draw_* and fill_* function works on it.
+pub struct Blended<'a> {
+	bitmap: &'a mut Vec<u8>,
+	opacity: f64,
+	blend_mode: BlendMode,
+}
+
+pub trait Blend {
+	fn blended(&mut self, opacity: f64, blend_mode: BlendMode) -> Blended<'_>;
+}
+
+impl Blend for Vec<u8> {
+	// Opacity runs from 0.0 (invisible) to 1.0 (opaque), as for Canvas::set_opacity
+	fn blended(&mut self, opacity: f64, blend_mode: BlendMode) -> Blended<'_> {
+		Blended { bitmap: self, opacity: opacity.clamp(0.0, 1.0), blend_mode }
+	}
+}
+
+impl Bitmap for Blended<'_> {
+	fn new_bitmap(width: u32, height: u32, bpp: u16) -> Vec<u8> {
+		Vec::<u8>::new_bitmap(width, height, bpp)
+	}
+
+	fn point_exists(&self, point: &Point) -> bool {
+		self.bitmap.point_exists(point)
+	}
+
+	fn draw_point(&mut self, start: &Point, color: &Rgb) {
+		composite_point(self.bitmap, start, color, self.opacity, self.blend_mode);
+	}
+
+	fn clip_rect(&self) -> Rect {
+		self.bitmap.clip_rect()
+	}
+
+	fn has_file_signature(&self) -> bool {
+		self.bitmap.has_file_signature()
+	}
+}
+
+#[test]
+fn test_composite_modes() {
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let gray = Rgb {r: 128, g: 128, b: 128};
+	let white = Rgb {r: 255, g: 255, b: 255};
+
+	assert_eq!(composite(&red, &gray, 1.0, BlendMode::SourceOver), red);
+	assert_eq!(composite(&red, &white, 0.5, BlendMode::SourceOver), Rgb {r: 255, g: 128, b: 128});
+	assert_eq!(composite(&red, &gray, 1.0, BlendMode::Multiply), Rgb {r: 128, g: 0, b: 0});
+	assert_eq!(composite(&red, &gray, 1.0, BlendMode::Screen), Rgb {r: 255, g: 128, b: 128});
+	assert_eq!(composite(&gray, &gray, 1.0, BlendMode::Additive), Rgb {r: 255, g: 255, b: 255});
+	assert_eq!(composite(&white, &gray, 1.0, BlendMode::Xor), Rgb {r: 127, g: 127, b: 127});
+	assert_eq!(composite(&red, &gray, 1.0, BlendMode::DestinationOver), gray);
+	assert_eq!(composite(&red, &gray, 0.25, BlendMode::DestinationOut), Rgb {r: 96, g: 96, b: 96});
+	assert_eq!(composite(&red, &gray, 1.0, BlendMode::Clear), Rgb {r: 0, g: 0, b: 0});
+}
+
+#[test]
+fn test_blend_per_call() {
+	use crate::paint::*;
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(10, 10, 24);
+	bmp.fill_rectangle(&Point {x: 0, y: 0}, &Point {x: 9, y: 9}, &Paint::Solid(white));
+
+	// Each call picks its own opacity and mode, and plain drawing stays opaque
+	bmp.blended(0.5, BlendMode::SourceOver).fill_rectangle(&Point {x: 0, y: 0}, &Point {x: 4, y: 4}, &Paint::Solid(red));
+	bmp.blended(1.0, BlendMode::Multiply).draw_line(&Point {x: 0, y: 8}, &Point {x: 9, y: 8}, &Rgb {r: 128, g: 128, b: 128});
+	bmp.draw_point(&Point {x: 9, y: 0}, &red);
+	assert_eq!(bmp.get_pixel(&Point {x: 2, y: 2}).unwrap(), Rgb {r: 255, g: 128, b: 128});
+	assert_eq!(bmp.get_pixel(&Point {x: 7, y: 2}).unwrap(), white);
+	assert_eq!(bmp.get_pixel(&Point {x: 5, y: 8}).unwrap(), Rgb {r: 128, g: 128, b: 128});
+	assert_eq!(bmp.get_pixel(&Point {x: 9, y: 0}).unwrap(), red);
+}
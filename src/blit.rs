@@ -0,0 +1,173 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Rect;
+use crate::Get;
+use crate::signed::SignedPoint;
+use crate::blend::*;
+use crate::indexed::*;
+
+// Controls how source pixels are combined with the destination
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlitOptions {
+	// Source pixels of exactly this color are skipped
+	pub color_key: Option<Rgb>,
+	pub opacity: f64,
+	pub blend_mode: BlendMode,
+}
+
+impl Default for BlitOptions {
+	// An opaque copy of every pixel
+	fn default() -> BlitOptions {
+		BlitOptions { color_key: None, opacity: 1.0, blend_mode: BlendMode::SourceOver }
+	}
+}
+
+pub trait Blit {
+	// Bitmaps are always Vec<u8> in this crate, since Get is implemented for it
+	#[allow(clippy::ptr_arg)]
+	fn blit(&mut self, src: &Vec<u8>, src_rect: &Rect, dst: &SignedPoint, options: &BlitOptions);
+}
+
+impl Blit for Vec<u8> {
+	// Copies src_rect from src so that its lowest corner lands on dst. Parts falling outside either image are skipped.
+	fn blit(&mut self, src: &Vec<u8>, src_rect: &Rect, dst: &SignedPoint, options: &BlitOptions) {
+		if !self.has_file_signature() || !src.has_file_signature() {
+			eprintln!("Invalid file signature. Not a Bitmap.");
+			return;
+		}
+
+		// Clip against the source, moving the destination by however much was cut off
+		let clipped = src_rect.intersect(&src.clip_rect());
+		let mut src_x = clipped.x as i64;
+		let mut src_y = clipped.y as i64;
+		let mut dst_x = dst.x as i64 + (clipped.x as i64 - src_rect.x as i64);
+		let mut dst_y = dst.y as i64 + (clipped.y as i64 - src_rect.y as i64);
+		let mut width = clipped.width as i64;
+		let mut height = clipped.height as i64;
+
+		// Clip against the destination
+		if dst_x < 0 {
+			src_x -= dst_x;
+			width += dst_x;
+			dst_x = 0;
+		}
+		if dst_y < 0 {
+			src_y -= dst_y;
+			height += dst_y;
+			dst_y = 0;
+		}
+		width = width.min(self.get_width() as i64 - dst_x);
+		height = height.min(self.get_height() as i64 - dst_y);
+		if width <= 0 || height <= 0 {
+			return;
+		}
+
+		let src_bpp = src.get_bits_per_pixel() as usize;
+		let dst_bpp = self.get_bits_per_pixel() as usize;
+		let opaque_copy = options.color_key.is_none() && options.opacity >= 1.0 && options.blend_mode == BlendMode::SourceOver;
+
+		// Other depths go through the generic pixel accessors.
+		// Indexed images sharing a palette exchange color indices, so plain copies stay exact.
+		if !(src_bpp == 24 || src_bpp == 32) || !(dst_bpp == 24 || dst_bpp == 32) {
+			let copy_indices = opaque_copy && src.is_indexed() && self.is_indexed() && src.get_palette() == self.get_palette();
+			for row in 0..height {
+				for column in 0..width {
+					let source_point = Point { x: (src_x + column) as u32, y: (src_y + row) as u32 };
+					let target_point = Point { x: (dst_x + column) as u32, y: (dst_y + row) as u32 };
+					if copy_indices {
+						if let Ok(index) = src.get_pixel_index(&source_point) {
+							self.set_pixel_index(&target_point, index);
+						}
+						continue;
+					}
+					if let (Ok(source), Ok(target)) = (src.get_pixel(&source_point), self.get_pixel(&target_point)) {
+						if options.color_key == Some(source) {
+							continue;
+						}
+						self.draw_point(&target_point, &composite(&source, &target, options.opacity, options.blend_mode));
+					}
+				}
+			}
+			return;
+		}
+
+		let src_pixel_size = src_bpp / 8;
+		let dst_pixel_size = dst_bpp / 8;
+		let src_stride = src.get_row_size();
+		let dst_stride = self.get_row_size();
+		let src_offset = src.get_pixel_array_offset();
+		let dst_offset = self.get_pixel_array_offset();
+
+		// Work a scan line at a time directly on the padded pixel arrays
+		for row in 0..height as usize {
+			let src_start = src_offset + (src_y as usize + row) * src_stride + src_x as usize * src_pixel_size;
+			let dst_start = dst_offset + (dst_y as usize + row) * dst_stride + dst_x as usize * dst_pixel_size;
+			let src_end = src_start + width as usize * src_pixel_size;
+			let dst_end = dst_start + width as usize * dst_pixel_size;
+			if src_end > src.len() || dst_end > self.len() {
+				eprintln!("Index out of bounds: {}", dst_end);
+				return;
+			}
+
+			if opaque_copy && src_pixel_size == dst_pixel_size {
+				self[dst_start..dst_end].copy_from_slice(&src[src_start..src_end]);
+				continue;
+			}
+
+			for column in 0..width as usize {
+				let s = src_start + column * src_pixel_size;
+				let d = dst_start + column * dst_pixel_size;
+				let source = Rgb { r: src[s + 2], g: src[s + 1], b: src[s] };
+				if options.color_key == Some(source) {
+					continue;
+				}
+				let color = if opaque_copy {
+					source
+				} else {
+					let target = Rgb { r: self[d + 2], g: self[d + 1], b: self[d] };
+					composite(&source, &target, options.opacity, options.blend_mode)
+				};
+				self[d] = color.b;
+				self[d + 1] = color.g;
+				self[d + 2] = color.r;
+			}
+		}
+	}
+}
+
+#[test]
+fn test_blit_clipping_and_color_key() {
+	use crate::{Paint, PaintFill};
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let green = Rgb {r: 0, g: 255, b: 0};
+	let black = Rgb {r: 0, g: 0, b: 0};
+
+	// A 4x4 red square with a green center pixel on a 32bpp source
+	let mut src:Vec<u8> = Vec::<u8>::new_bitmap(4, 4, 32);
+	src.fill_rectangle(&Point {x: 0, y: 0}, &Point {x: 3, y: 3}, &Paint::Solid(red));
+	src.draw_point(&Point {x: 2, y: 2}, &green);
+
+	let mut dst:Vec<u8> = Vec::<u8>::new_bitmap(5, 5, 24);
+	let whole = Rect {x: 0, y: 0, width: 4, height: 4};
+	dst.blit(&src, &whole, &SignedPoint {x: -1, y: 3}, &BlitOptions::default());
+	assert_eq!(dst.get_pixel(&Point {x: 0, y: 3}).unwrap(), red);
+	assert_eq!(dst.get_pixel(&Point {x: 2, y: 4}).unwrap(), red);
+	assert_eq!(dst.get_pixel(&Point {x: 3, y: 4}).unwrap(), black);
+	assert_eq!(dst.get_pixel(&Point {x: 1, y: 2}).unwrap(), black);
+
+	// Keying out red leaves only the green pixel
+	let mut dst:Vec<u8> = Vec::<u8>::new_bitmap(5, 5, 24);
+	let options = BlitOptions { color_key: Some(red), ..BlitOptions::default() };
+	dst.blit(&src, &whole, &SignedPoint {x: 1, y: 1}, &options);
+	assert_eq!(dst.get_pixel(&Point {x: 3, y: 3}).unwrap(), green);
+	assert_eq!(dst.get_pixel(&Point {x: 1, y: 1}).unwrap(), black);
+
+	// Half transparent copy between two 24bpp images uses the blending path
+	let mut dst:Vec<u8> = Vec::<u8>::new_bitmap(5, 5, 24);
+	let copy = dst.clone();
+	dst.draw_point(&Point {x: 0, y: 0}, &green);
+	let options = BlitOptions { opacity: 0.5, ..BlitOptions::default() };
+	dst.blit(&copy, &Rect {x: 0, y: 0, width: 1, height: 1}, &SignedPoint {x: 0, y: 0}, &options);
+	assert_eq!(dst.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 0, g: 128, b: 0});
+}
@@ -0,0 +1,295 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Rect;
+use crate::Get;
+use crate::signed::bresenham;
+use crate::blend::*;
+
+// Region codes for Cohen–Sutherland line clipping
+const INSIDE: u8 = 0;
+const LEFT: u8 = 1;
+const RIGHT: u8 = 2;
+const BOTTOM: u8 = 4;
+const TOP: u8 = 8;
+
+fn region_code(x: f64, y: f64, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> u8 {
+	let mut code = INSIDE;
+	if x < min_x {
+		code |= LEFT;
+	} else if x > max_x {
+		code |= RIGHT;
+	}
+	if y < min_y {
+		code |= BOTTOM;
+	} else if y > max_y {
+		code |= TOP;
+	}
+	code
+}
+
+// Clips the segment from (x0, y0) to (x1, y1) to the pixels of rect using the Cohen–Sutherland algorithm.
+// Returns None when no part of the segment is inside.
+pub fn clip_line(x0: i64, y0: i64, x1: i64, y1: i64, rect: &Rect) -> Option<(i64, i64, i64, i64)> {
+	if rect.is_empty() {
+		return None;
+	}
+	let min_x = rect.x as f64;
+	let min_y = rect.y as f64;
+	let max_x = rect.x as f64 + rect.width as f64 - 1.0;
+	let max_y = rect.y as f64 + rect.height as f64 - 1.0;
+
+	let (mut x0, mut y0, mut x1, mut y1) = (x0 as f64, y0 as f64, x1 as f64, y1 as f64);
+	let mut code0 = region_code(x0, y0, min_x, min_y, max_x, max_y);
+	let mut code1 = region_code(x1, y1, min_x, min_y, max_x, max_y);
+	loop {
+		if code0 | code1 == INSIDE {
+			return Some((x0.round() as i64, y0.round() as i64, x1.round() as i64, y1.round() as i64));
+		}
+		// Both endpoints share an outside region, so the segment cannot cross the rectangle
+		if code0 & code1 != INSIDE {
+			return None;
+		}
+
+		// Move the outside endpoint onto the edge it lies beyond
+		let code = if code0 != INSIDE { code0 } else { code1 };
+		let (x, y);
+		if code & TOP != 0 {
+			x = x0 + (x1 - x0) * (max_y - y0) / (y1 - y0);
+			y = max_y;
+		} else if code & BOTTOM != 0 {
+			x = x0 + (x1 - x0) * (min_y - y0) / (y1 - y0);
+			y = min_y;
+		} else if code & RIGHT != 0 {
+			y = y0 + (y1 - y0) * (max_x - x0) / (x1 - x0);
+			x = max_x;
+		} else {
+			y = y0 + (y1 - y0) * (min_x - x0) / (x1 - x0);
+			x = min_x;
+		}
+
+		if code == code0 {
+			x0 = x;
+			y0 = y;
+			code0 = region_code(x0, y0, min_x, min_y, max_x, max_y);
+		} else {
+			x1 = x;
+			y1 = y;
+			code1 = region_code(x1, y1, min_x, min_y, max_x, max_y);
+		}
+	}
+}
+
+// Draws a line after clipping it to the bitmap's clip rectangle, so only visible pixels are visited
+pub(crate) fn draw_clipped_line<T: Bitmap + ?Sized>(bmp: &mut T, x0: i64, y0: i64, x1: i64, y1: i64, color: &Rgb) {
+	let clip = bmp.clip_rect();
+	if let Some((x0, y0, x1, y1)) = clip_line(x0, y0, x1, y1, &clip) {
+		bresenham(x0, y0, x1, y1, |x, y| {
+			// Rounding the clipped endpoints can step just outside the rectangle
+			if clip.contains(x, y) {
+				bmp.draw_point(&Point { x: x as u32, y: y as u32 }, color);
+			}
+		});
+	}
+}
+
+// Per-pixel visibility for arbitrarily shaped clipping
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipMask {
+	width: u32,
+	height: u32,
+	visible: Vec<bool>,
+}
+
+impl ClipMask {
+	// Creates a mask of the given size with every pixel hidden
+	pub fn new(width: u32, height: u32) -> ClipMask {
+		ClipMask { width, height, visible: vec![false; width as usize * height as usize] }
+	}
+
+	// Creates a mask from a bitmap, where every pixel that is not black is visible
+	pub fn from_bitmap(mask: &Vec<u8>) -> ClipMask {
+		let width = mask.get_width();
+		let height = mask.get_height();
+		let mut clip_mask = ClipMask::new(width, height);
+		for y in 0..height {
+			for x in 0..width {
+				if let Ok(pixel) = mask.get_pixel(&Point { x, y }) {
+					clip_mask.set(x, y, pixel != Rgb { r: 0, g: 0, b: 0 });
+				}
+			}
+		}
+		clip_mask
+	}
+
+	pub fn set(&mut self, x: u32, y: u32, visible: bool) {
+		if x < self.width && y < self.height {
+			self.visible[y as usize * self.width as usize + x as usize] = visible;
+		}
+	}
+
+	// Pixels outside the mask are hidden
+	pub fn is_visible(&self, x: u32, y: u32) -> bool {
+		x < self.width && y < self.height && self.visible[y as usize * self.width as usize + x as usize]
+	}
+}
+
+// Wraps a bitmap so every drawing operation is restricted to a clip rectangle and, optionally, a clip mask,
+// and is composited onto the existing pixels with an opacity and blend mode.
+// Canvas implements Bitmap, so all draw_* functions work on it unchanged.
+pub struct Canvas<'a> {
+	bitmap: &'a mut Vec<u8>,
+	clip: Rect,
+	mask: Option<ClipMask>,
+	opacity: f64,
+	blend_mode: BlendMode,
+}
+
+impl<'a> Canvas<'a> {
+	// Starts with the whole image drawable
+	pub fn new(bitmap: &'a mut Vec<u8>) -> Canvas<'a> {
+		let clip = bitmap.clip_rect();
+		Canvas { bitmap, clip, mask: None, opacity: 1.0, blend_mode: BlendMode::SourceOver }
+	}
+
+	// Restricts drawing to rect, intersected with the image bounds
+	pub fn set_clip_rect(&mut self, rect: &Rect) {
+		self.clip = self.bitmap.clip_rect().intersect(rect);
+	}
+
+	// Makes the whole image drawable again and removes any mask
+	pub fn reset_clip(&mut self) {
+		self.clip = self.bitmap.clip_rect();
+		self.mask = None;
+	}
+
+	pub fn set_clip_mask(&mut self, mask: Option<ClipMask>) {
+		self.mask = mask;
+	}
+
+	// Opacity of everything drawn from now on, from 0.0 (invisible) to 1.0 (opaque)
+	pub fn set_opacity(&mut self, opacity: f64) {
+		self.opacity = opacity.clamp(0.0, 1.0);
+	}
+
+	pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+		self.blend_mode = blend_mode;
+	}
+
+	pub fn bitmap(&self) -> &Vec<u8> {
+		self.bitmap
+	}
+}
+
+impl Bitmap for Canvas<'_> {
+	fn new_bitmap(width: u32, height: u32, bpp: u16) -> Vec<u8> {
+		Vec::<u8>::new_bitmap(width, height, bpp)
+	}
+
+	// A point exists on a canvas when it is inside the clip rectangle and visible through the mask
+	fn point_exists(&self, point: &Point) -> bool {
+		if !self.clip.contains(point.x as i64, point.y as i64) {
+			return false;
+		}
+		match &self.mask {
+			Some(mask) => mask.is_visible(point.x, point.y),
+			None => true,
+		}
+	}
+
+	fn draw_point(&mut self, start: &Point, color: &Rgb) {
+		if !self.point_exists(start) {
+			return;
+		}
+		composite_point(self.bitmap, start, color, self.opacity, self.blend_mode);
+	}
+
+	fn clip_rect(&self) -> Rect {
+		self.clip
+	}
+
+	fn has_file_signature(&self) -> bool {
+		self.bitmap.has_file_signature()
+	}
+}
+
+#[test]
+fn test_clip_line() {
+	let rect = Rect {x: 0, y: 0, width: 10, height: 10};
+	assert_eq!(clip_line(-5, 5, 20, 5, &rect), Some((0, 5, 9, 5)));
+	assert_eq!(clip_line(-1000000, -1000000, 1000000, 1000000, &rect), Some((0, 0, 9, 9)));
+	assert_eq!(clip_line(-5, -5, -1, 20, &rect), None);
+	assert_eq!(clip_line(2, 3, 4, 5, &rect), Some((2, 3, 4, 5)));
+}
+
+#[test]
+fn test_canvas_clipping() {
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(20, 20, 24);
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let black = Rgb {r: 0, g: 0, b: 0};
+	{
+		let mut canvas = Canvas::new(&mut bmp);
+		canvas.set_clip_rect(&Rect {x: 5, y: 5, width: 10, height: 10});
+		let mut mask = ClipMask::new(20, 20);
+		for y in 0..20 {
+			for x in 0..10 {
+				mask.set(x, y, true);
+			}
+		}
+		canvas.set_clip_mask(Some(mask));
+		canvas.draw_circle(&Point {x: 10, y: 10}, 30, &red);
+	}
+	assert_eq!(bmp.get_pixel(&Point {x: 5, y: 5}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 9, y: 14}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 4, y: 10}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 10, y: 10}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 7, y: 15}).unwrap(), black);
+}
+
+#[test]
+fn test_canvas_blending() {
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(10, 10, 24);
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let blue = Rgb {r: 0, g: 0, b: 255};
+	bmp.draw_circle(&Point {x: 5, y: 5}, 10, &white);
+	{
+		let mut canvas = Canvas::new(&mut bmp);
+		canvas.set_opacity(0.5);
+		canvas.draw_line(&Point {x: 0, y: 0}, &Point {x: 9, y: 0}, &blue);
+		canvas.set_opacity(1.0);
+		canvas.set_blend_mode(BlendMode::Multiply);
+		canvas.draw_line(&Point {x: 0, y: 1}, &Point {x: 9, y: 1}, &blue);
+	}
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 0}).unwrap(), Rgb {r: 128, g: 128, b: 255});
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 1}).unwrap(), blue);
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 2}).unwrap(), white);
+}
+
+#[test]
+fn test_default_clip_rect() {
+	// An implementation written before clip_rect existed still compiles and can fill shapes
+	struct Grid {
+		cells: Vec<bool>,
+	}
+	impl Bitmap for Grid {
+		fn new_bitmap(width: u32, height: u32, bpp: u16) -> Vec<u8> {
+			Vec::<u8>::new_bitmap(width, height, bpp)
+		}
+		fn point_exists(&self, point: &Point) -> bool {
+			point.x < 4 && point.y < 4
+		}
+		fn draw_point(&mut self, start: &Point, _color: &Rgb) {
+			if self.point_exists(start) {
+				self.cells[(start.y * 4 + start.x) as usize] = true;
+			}
+		}
+		fn has_file_signature(&self) -> bool {
+			true
+		}
+	}
+	use crate::paint::*;
+	let mut grid = Grid { cells: vec![false; 16] };
+	grid.fill_rectangle(&Point {x: 2, y: 2}, &Point {x: 9, y: 9}, &Paint::Solid(Rgb {r: 255, g: 255, b: 255}));
+	assert_eq!(grid.cells.iter().filter(|&&cell| cell).count(), 4);
+	assert!(grid.cells[15]);
+}
@@ -0,0 +1,516 @@
+use std::str::FromStr;
+use crate::Rgb;
+
+// Hue in degrees 0..360, saturation and value in 0..1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsv {
+	pub h: f64,
+	pub s: f64,
+	pub v: f64,
+}
+
+// Hue in degrees 0..360, saturation and lightness in 0..1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsl {
+	pub h: f64,
+	pub s: f64,
+	pub l: f64,
+}
+
+// CIE 1931 XYZ relative to the D65 white point, with white at y = 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Xyz {
+	pub x: f64,
+	pub y: f64,
+	pub z: f64,
+}
+
+// CIELAB relative to D65, lightness l in 0..100
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lab {
+	pub l: f64,
+	pub a: f64,
+	pub b: f64,
+}
+
+// Björn Ottosson's OKLab, lightness l in 0..1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Oklab {
+	pub l: f64,
+	pub a: f64,
+	pub b: f64,
+}
+
+// The space in which Rgb::interpolate blends two colors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+	// Straight on the stored sRGB values
+	Srgb,
+	// On linear light intensities, which avoids the dark band between saturated colors
+	LinearRgb,
+	// Hue takes the shorter way around the color wheel
+	Hsv,
+	Hsl,
+	Lab,
+	// Usually the most even looking choice for gradients
+	Oklab,
+}
+
+const D65_WHITE: [f64; 3] = [0.95047, 1.0, 1.08883];
+
+fn to_u8(value: f64) -> u8 {
+	value.round().clamp(0.0, 255.0) as u8
+}
+
+// sRGB transfer function, from an encoded 0..255 channel to linear light in 0..1
+fn srgb_to_linear(value: u8) -> f64 {
+	let c = value as f64 / 255.0;
+	if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(value: f64) -> u8 {
+	let c = if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.max(0.0).powf(1.0 / 2.4) - 0.055 };
+	to_u8(c * 255.0)
+}
+
+fn linear_rgb(color: &Rgb) -> [f64; 3] {
+	[srgb_to_linear(color.r), srgb_to_linear(color.g), srgb_to_linear(color.b)]
+}
+
+fn from_linear_rgb(linear: [f64; 3]) -> Rgb {
+	Rgb { r: linear_to_srgb(linear[0]), g: linear_to_srgb(linear[1]), b: linear_to_srgb(linear[2]) }
+}
+
+// Red, green and blue in 0..1 for a hue in degrees and the chroma and offset shared by HSV and HSL
+fn hue_to_rgb(hue: f64, chroma: f64, offset: f64) -> Rgb {
+	let h = hue.rem_euclid(360.0) / 60.0;
+	let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+	let (r, g, b) = match h as u32 {
+		0 => (chroma, x, 0.0),
+		1 => (x, chroma, 0.0),
+		2 => (0.0, chroma, x),
+		3 => (0.0, x, chroma),
+		4 => (x, 0.0, chroma),
+		_ => (chroma, 0.0, x),
+	};
+	Rgb { r: to_u8((r + offset) * 255.0), g: to_u8((g + offset) * 255.0), b: to_u8((b + offset) * 255.0) }
+}
+
+// Hue in degrees together with the largest and smallest channel in 0..1
+fn rgb_to_hue(color: &Rgb) -> (f64, f64, f64) {
+	let r = color.r as f64 / 255.0;
+	let g = color.g as f64 / 255.0;
+	let b = color.b as f64 / 255.0;
+	let max = r.max(g).max(b);
+	let min = r.min(g).min(b);
+	let chroma = max - min;
+	let hue = if chroma == 0.0 {
+		0.0
+	} else if max == r {
+		60.0 * ((g - b) / chroma).rem_euclid(6.0)
+	} else if max == g {
+		60.0 * ((b - r) / chroma + 2.0)
+	} else {
+		60.0 * ((r - g) / chroma + 4.0)
+	};
+	(hue, max, min)
+}
+
+impl From<Rgb> for Hsv {
+	fn from(color: Rgb) -> Hsv {
+		let (h, max, min) = rgb_to_hue(&color);
+		let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+		Hsv { h, s, v: max }
+	}
+}
+
+impl From<Hsv> for Rgb {
+	fn from(color: Hsv) -> Rgb {
+		let chroma = color.v * color.s;
+		hue_to_rgb(color.h, chroma, color.v - chroma)
+	}
+}
+
+impl From<Rgb> for Hsl {
+	fn from(color: Rgb) -> Hsl {
+		let (h, max, min) = rgb_to_hue(&color);
+		let l = (max + min) / 2.0;
+		let s = if max == min { 0.0 } else { (max - min) / (1.0 - (2.0 * l - 1.0).abs()) };
+		Hsl { h, s, l }
+	}
+}
+
+impl From<Hsl> for Rgb {
+	fn from(color: Hsl) -> Rgb {
+		let chroma = (1.0 - (2.0 * color.l - 1.0).abs()) * color.s;
+		hue_to_rgb(color.h, chroma, color.l - chroma / 2.0)
+	}
+}
+
+impl From<Rgb> for Xyz {
+	fn from(color: Rgb) -> Xyz {
+		let [r, g, b] = linear_rgb(&color);
+		Xyz {
+			x: 0.4124564 * r + 0.3575761 * g + 0.1804375 * b,
+			y: 0.2126729 * r + 0.7151522 * g + 0.0721750 * b,
+			z: 0.0193339 * r + 0.1191920 * g + 0.9503041 * b,
+		}
+	}
+}
+
+impl From<Xyz> for Rgb {
+	fn from(color: Xyz) -> Rgb {
+		from_linear_rgb([
+			3.2404542 * color.x - 1.5371385 * color.y - 0.4985314 * color.z,
+			-0.9692660 * color.x + 1.8760108 * color.y + 0.0415560 * color.z,
+			0.0556434 * color.x - 0.2040259 * color.y + 1.0572252 * color.z,
+		])
+	}
+}
+
+impl From<Xyz> for Lab {
+	fn from(color: Xyz) -> Lab {
+		let f = |t: f64| if t > 216.0 / 24389.0 { t.cbrt() } else { (24389.0 / 27.0 * t + 16.0) / 116.0 };
+		let fx = f(color.x / D65_WHITE[0]);
+		let fy = f(color.y / D65_WHITE[1]);
+		let fz = f(color.z / D65_WHITE[2]);
+		Lab { l: 116.0 * fy - 16.0, a: 500.0 * (fx - fy), b: 200.0 * (fy - fz) }
+	}
+}
+
+impl From<Lab> for Xyz {
+	fn from(color: Lab) -> Xyz {
+		let fy = (color.l + 16.0) / 116.0;
+		let fx = fy + color.a / 500.0;
+		let fz = fy - color.b / 200.0;
+		let f_inverse = |t: f64| if t * t * t > 216.0 / 24389.0 { t * t * t } else { (116.0 * t - 16.0) * 27.0 / 24389.0 };
+		Xyz { x: f_inverse(fx) * D65_WHITE[0], y: f_inverse(fy) * D65_WHITE[1], z: f_inverse(fz) * D65_WHITE[2] }
+	}
+}
+
+impl From<Rgb> for Lab {
+	fn from(color: Rgb) -> Lab {
+		Lab::from(Xyz::from(color))
+	}
+}
+
+impl From<Lab> for Rgb {
+	fn from(color: Lab) -> Rgb {
+		Rgb::from(Xyz::from(color))
+	}
+}
+
+impl From<Rgb> for Oklab {
+	fn from(color: Rgb) -> Oklab {
+		let [r, g, b] = linear_rgb(&color);
+		let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+		let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+		let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+		Oklab {
+			l: 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+			a: 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+			b: 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+		}
+	}
+}
+
+impl From<Oklab> for Rgb {
+	fn from(color: Oklab) -> Rgb {
+		let l = color.l + 0.3963377774 * color.a + 0.2158037573 * color.b;
+		let m = color.l - 0.1055613458 * color.a - 0.0638541728 * color.b;
+		let s = color.l - 0.0894841775 * color.a - 1.2914855480 * color.b;
+		let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+		from_linear_rgb([
+			4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+			-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+			-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+		])
+	}
+}
+
+// Blends two hues along the shorter arc
+fn lerp_hue(a: f64, b: f64, t: f64) -> f64 {
+	let difference = (b - a + 180.0).rem_euclid(360.0) - 180.0;
+	(a + difference * t).rem_euclid(360.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+	a + (b - a) * t
+}
+
+impl Rgb {
+	// Accepts "#rrggbb", "#rgb" and the same without the leading '#'
+	pub fn from_hex(hex: &str) -> Result<Rgb, String> {
+		let digits = hex.trim().trim_start_matches('#');
+		let invalid = || format!("Invalid hex color \"{}\"", hex);
+		if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+			return Err(invalid());
+		}
+		let channel = |i: usize, len: usize| u8::from_str_radix(&digits[i * len..(i + 1) * len], 16).map_err(|_| invalid());
+		match digits.len() {
+			6 => Ok(Rgb { r: channel(0, 2)?, g: channel(1, 2)?, b: channel(2, 2)? }),
+			// Each digit is doubled, so "#f80" is "#ff8800"
+			3 => Ok(Rgb { r: channel(0, 1)? * 17, g: channel(1, 1)? * 17, b: channel(2, 1)? * 17 }),
+			_ => Err(invalid()),
+		}
+	}
+
+	// Formats as lowercase "#rrggbb"
+	pub fn to_hex(&self) -> String {
+		format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+	}
+
+	// Looks up one of the CSS named colors, ignoring case
+	pub fn named(name: &str) -> Option<Rgb> {
+		let name = name.trim().to_ascii_lowercase();
+		CSS_COLORS.iter().find(|(css_name, _)| *css_name == name).map(|(_, value)| Rgb {
+			r: (value >> 16) as u8,
+			g: (value >> 8) as u8,
+			b: *value as u8,
+		})
+	}
+
+	// Blends towards other by t in 0..1, computed in the given color space
+	pub fn interpolate(&self, other: &Rgb, t: f64, space: ColorSpace) -> Rgb {
+		match space {
+			ColorSpace::Srgb => Rgb {
+				r: to_u8(lerp(self.r as f64, other.r as f64, t)),
+				g: to_u8(lerp(self.g as f64, other.g as f64, t)),
+				b: to_u8(lerp(self.b as f64, other.b as f64, t)),
+			},
+			ColorSpace::LinearRgb => {
+				let a = linear_rgb(self);
+				let b = linear_rgb(other);
+				from_linear_rgb([lerp(a[0], b[0], t), lerp(a[1], b[1], t), lerp(a[2], b[2], t)])
+			}
+			ColorSpace::Hsv => {
+				let a = Hsv::from(*self);
+				let b = Hsv::from(*other);
+				Rgb::from(Hsv { h: lerp_hue(a.h, b.h, t), s: lerp(a.s, b.s, t), v: lerp(a.v, b.v, t) })
+			}
+			ColorSpace::Hsl => {
+				let a = Hsl::from(*self);
+				let b = Hsl::from(*other);
+				Rgb::from(Hsl { h: lerp_hue(a.h, b.h, t), s: lerp(a.s, b.s, t), l: lerp(a.l, b.l, t) })
+			}
+			ColorSpace::Lab => {
+				let a = Lab::from(*self);
+				let b = Lab::from(*other);
+				Rgb::from(Lab { l: lerp(a.l, b.l, t), a: lerp(a.a, b.a, t), b: lerp(a.b, b.b, t) })
+			}
+			ColorSpace::Oklab => {
+				let a = Oklab::from(*self);
+				let b = Oklab::from(*other);
+				Rgb::from(Oklab { l: lerp(a.l, b.l, t), a: lerp(a.a, b.a, t), b: lerp(a.b, b.b, t) })
+			}
+		}
+	}
+}
+
+// Parses either a hex color or a CSS color name
+impl FromStr for Rgb {
+	type Err = String;
+
+	fn from_str(text: &str) -> Result<Rgb, String> {
+		if let Some(color) = Rgb::named(text) {
+			return Ok(color);
+		}
+		Rgb::from_hex(text)
+	}
+}
+
+// The CSS Color Module Level 4 named colors as 0xRRGGBB
+const CSS_COLORS: [(&str, u32); 148] = [
+	("aliceblue", 0xf0f8ff),
+	("antiquewhite", 0xfaebd7),
+	("aqua", 0x00ffff),
+	("aquamarine", 0x7fffd4),
+	("azure", 0xf0ffff),
+	("beige", 0xf5f5dc),
+	("bisque", 0xffe4c4),
+	("black", 0x000000),
+	("blanchedalmond", 0xffebcd),
+	("blue", 0x0000ff),
+	("blueviolet", 0x8a2be2),
+	("brown", 0xa52a2a),
+	("burlywood", 0xdeb887),
+	("cadetblue", 0x5f9ea0),
+	("chartreuse", 0x7fff00),
+	("chocolate", 0xd2691e),
+	("coral", 0xff7f50),
+	("cornflowerblue", 0x6495ed),
+	("cornsilk", 0xfff8dc),
+	("crimson", 0xdc143c),
+	("cyan", 0x00ffff),
+	("darkblue", 0x00008b),
+	("darkcyan", 0x008b8b),
+	("darkgoldenrod", 0xb8860b),
+	("darkgray", 0xa9a9a9),
+	("darkgreen", 0x006400),
+	("darkgrey", 0xa9a9a9),
+	("darkkhaki", 0xbdb76b),
+	("darkmagenta", 0x8b008b),
+	("darkolivegreen", 0x556b2f),
+	("darkorange", 0xff8c00),
+	("darkorchid", 0x9932cc),
+	("darkred", 0x8b0000),
+	("darksalmon", 0xe9967a),
+	("darkseagreen", 0x8fbc8f),
+	("darkslateblue", 0x483d8b),
+	("darkslategray", 0x2f4f4f),
+	("darkslategrey", 0x2f4f4f),
+	("darkturquoise", 0x00ced1),
+	("darkviolet", 0x9400d3),
+	("deeppink", 0xff1493),
+	("deepskyblue", 0x00bfff),
+	("dimgray", 0x696969),
+	("dimgrey", 0x696969),
+	("dodgerblue", 0x1e90ff),
+	("firebrick", 0xb22222),
+	("floralwhite", 0xfffaf0),
+	("forestgreen", 0x228b22),
+	("fuchsia", 0xff00ff),
+	("gainsboro", 0xdcdcdc),
+	("ghostwhite", 0xf8f8ff),
+	("gold", 0xffd700),
+	("goldenrod", 0xdaa520),
+	("gray", 0x808080),
+	("green", 0x008000),
+	("greenyellow", 0xadff2f),
+	("grey", 0x808080),
+	("honeydew", 0xf0fff0),
+	("hotpink", 0xff69b4),
+	("indianred", 0xcd5c5c),
+	("indigo", 0x4b0082),
+	("ivory", 0xfffff0),
+	("khaki", 0xf0e68c),
+	("lavender", 0xe6e6fa),
+	("lavenderblush", 0xfff0f5),
+	("lawngreen", 0x7cfc00),
+	("lemonchiffon", 0xfffacd),
+	("lightblue", 0xadd8e6),
+	("lightcoral", 0xf08080),
+	("lightcyan", 0xe0ffff),
+	("lightgoldenrodyellow", 0xfafad2),
+	("lightgray", 0xd3d3d3),
+	("lightgreen", 0x90ee90),
+	("lightgrey", 0xd3d3d3),
+	("lightpink", 0xffb6c1),
+	("lightsalmon", 0xffa07a),
+	("lightseagreen", 0x20b2aa),
+	("lightskyblue", 0x87cefa),
+	("lightslategray", 0x778899),
+	("lightslategrey", 0x778899),
+	("lightsteelblue", 0xb0c4de),
+	("lightyellow", 0xffffe0),
+	("lime", 0x00ff00),
+	("limegreen", 0x32cd32),
+	("linen", 0xfaf0e6),
+	("magenta", 0xff00ff),
+	("maroon", 0x800000),
+	("mediumaquamarine", 0x66cdaa),
+	("mediumblue", 0x0000cd),
+	("mediumorchid", 0xba55d3),
+	("mediumpurple", 0x9370db),
+	("mediumseagreen", 0x3cb371),
+	("mediumslateblue", 0x7b68ee),
+	("mediumspringgreen", 0x00fa9a),
+	("mediumturquoise", 0x48d1cc),
+	("mediumvioletred", 0xc71585),
+	("midnightblue", 0x191970),
+	("mintcream", 0xf5fffa),
+	("mistyrose", 0xffe4e1),
+	("moccasin", 0xffe4b5),
+	("navajowhite", 0xffdead),
+	("navy", 0x000080),
+	("oldlace", 0xfdf5e6),
+	("olive", 0x808000),
+	("olivedrab", 0x6b8e23),
+	("orange", 0xffa500),
+	("orangered", 0xff4500),
+	("orchid", 0xda70d6),
+	("palegoldenrod", 0xeee8aa),
+	("palegreen", 0x98fb98),
+	("paleturquoise", 0xafeeee),
+	("palevioletred", 0xdb7093),
+	("papayawhip", 0xffefd5),
+	("peachpuff", 0xffdab9),
+	("peru", 0xcd853f),
+	("pink", 0xffc0cb),
+	("plum", 0xdda0dd),
+	("powderblue", 0xb0e0e6),
+	("purple", 0x800080),
+	("rebeccapurple", 0x663399),
+	("red", 0xff0000),
+	("rosybrown", 0xbc8f8f),
+	("royalblue", 0x4169e1),
+	("saddlebrown", 0x8b4513),
+	("salmon", 0xfa8072),
+	("sandybrown", 0xf4a460),
+	("seagreen", 0x2e8b57),
+	("seashell", 0xfff5ee),
+	("sienna", 0xa0522d),
+	("silver", 0xc0c0c0),
+	("skyblue", 0x87ceeb),
+	("slateblue", 0x6a5acd),
+	("slategray", 0x708090),
+	("slategrey", 0x708090),
+	("snow", 0xfffafa),
+	("springgreen", 0x00ff7f),
+	("steelblue", 0x4682b4),
+	("tan", 0xd2b48c),
+	("teal", 0x008080),
+	("thistle", 0xd8bfd8),
+	("tomato", 0xff6347),
+	("turquoise", 0x40e0d0),
+	("violet", 0xee82ee),
+	("wheat", 0xf5deb3),
+	("white", 0xffffff),
+	("whitesmoke", 0xf5f5f5),
+	("yellow", 0xffff00),
+	("yellowgreen", 0x9acd32),
+];
+
+#[test]
+fn test_color_space_round_trips() {
+	for color in [Rgb {r: 255, g: 136, b: 0}, Rgb {r: 12, g: 200, b: 99}, Rgb {r: 0, g: 0, b: 0}, Rgb {r: 255, g: 255, b: 255}, Rgb {r: 70, g: 70, b: 200}] {
+		assert_eq!(Rgb::from(Hsv::from(color)), color);
+		assert_eq!(Rgb::from(Hsl::from(color)), color);
+		assert_eq!(Rgb::from(Xyz::from(color)), color);
+		assert_eq!(Rgb::from(Lab::from(color)), color);
+		assert_eq!(Rgb::from(Oklab::from(color)), color);
+	}
+
+	let hsv = Hsv::from(Rgb {r: 0, g: 255, b: 0});
+	assert_eq!((hsv.h, hsv.s, hsv.v), (120.0, 1.0, 1.0));
+	let white = Lab::from(Rgb {r: 255, g: 255, b: 255});
+	assert!((white.l - 100.0).abs() < 1e-3 && white.a.abs() < 1e-2 && white.b.abs() < 1e-2);
+	let white = Oklab::from(Rgb {r: 255, g: 255, b: 255});
+	assert!((white.l - 1.0).abs() < 1e-3 && white.a.abs() < 1e-3 && white.b.abs() < 1e-3);
+}
+
+#[test]
+fn test_hex_names_and_interpolation() {
+	let orange = Rgb {r: 255, g: 136, b: 0};
+	assert_eq!(Rgb::from_hex("#ff8800").unwrap(), orange);
+	assert_eq!(Rgb::from_hex("F80").unwrap(), orange);
+	assert_eq!(orange.to_hex(), "#ff8800");
+	assert!(Rgb::from_hex("#ff88").is_err());
+	assert!(Rgb::from_hex("#gg8800").is_err());
+	assert_eq!(Rgb::named("RebeccaPurple"), Some(Rgb {r: 102, g: 51, b: 153}));
+	assert_eq!(Rgb::named("notacolor"), None);
+	assert_eq!("tomato".parse::<Rgb>().unwrap(), Rgb {r: 255, g: 99, b: 71});
+	assert_eq!("#000".parse::<Rgb>().unwrap(), Rgb {r: 0, g: 0, b: 0});
+
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let blue = Rgb {r: 0, g: 0, b: 255};
+	assert_eq!(red.interpolate(&blue, 0.5, ColorSpace::Srgb), Rgb {r: 128, g: 0, b: 128});
+	// Hue goes the short way from red to blue, through magenta rather than green
+	assert_eq!(red.interpolate(&blue, 0.5, ColorSpace::Hsv), Rgb {r: 255, g: 0, b: 255});
+	for space in [ColorSpace::Srgb, ColorSpace::LinearRgb, ColorSpace::Hsv, ColorSpace::Hsl, ColorSpace::Lab, ColorSpace::Oklab] {
+		assert_eq!(red.interpolate(&blue, 0.0, space), red);
+		assert_eq!(red.interpolate(&blue, 1.0, space), blue);
+	}
+	// Linear light keeps the blend brighter than the plain sRGB average
+	assert!(red.interpolate(&blue, 0.5, ColorSpace::LinearRgb).r > 128);
+}
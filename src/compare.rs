@@ -0,0 +1,176 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::adjust::LumaWeights;
+use crate::fill::color_within_tolerance;
+use crate::filter::*;
+use crate::resample::read_pixels;
+
+// Both images must be bitmaps of the same size to be compared pixel by pixel
+fn check_comparable(a: &Vec<u8>, b: &Vec<u8>) -> Result<(u32, u32), String> {
+	if !a.has_file_signature() || !b.has_file_signature() {
+		return Err("Invalid file signature. Not a Bitmap.".to_string());
+	}
+	if a.get_width() != b.get_width() || a.get_height() != b.get_height() {
+		return Err(format!("Cannot compare a {}x{} image with a {}x{} image", a.get_width(), a.get_height(), b.get_width(), b.get_height()));
+	}
+	Ok((a.get_width(), a.get_height()))
+}
+
+// Structural similarity of two luma planes, averaged over Gaussian windows with sigma 1.5
+fn ssim_luma(a: &[f64], b: &[f64], width: u32, height: u32) -> f64 {
+	const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+	const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+	// The window statistics are computed as blurred planes; the three channels carry a, b and a * b
+	let weights = gaussian_weights(1.5);
+	let plane: Vec<[f64; 3]> = a.iter().zip(b.iter()).map(|(&a, &b)| [a, b, a * b]).collect();
+	let squares: Vec<[f64; 3]> = a.iter().zip(b.iter()).map(|(&a, &b)| [a * a, b * b, 0.0]).collect();
+	let means = convolve_separable(&plane, width, height, &weights, EdgeMode::Mirror);
+	let energies = convolve_separable(&squares, width, height, &weights, EdgeMode::Mirror);
+	let total: f64 = means.iter().zip(energies.iter()).map(|(mean, energy)| {
+		let (mean_a, mean_b) = (mean[0], mean[1]);
+		let variance_a = energy[0] - mean_a * mean_a;
+		let variance_b = energy[1] - mean_b * mean_b;
+		let covariance = mean[2] - mean_a * mean_b;
+		((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2)) / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2))
+	}).sum();
+	total / a.len() as f64
+}
+
+// Bitmaps are passed around as &Vec<u8> throughout the crate
+#[allow(clippy::ptr_arg)]
+pub trait Compare {
+	fn is_pixel_identical(&self, other: &Vec<u8>) -> bool;
+	fn count_differences(&self, other: &Vec<u8>, tolerance: u8) -> Result<u64, String>;
+	fn mse(&self, other: &Vec<u8>) -> Result<f64, String>;
+	fn psnr(&self, other: &Vec<u8>) -> Result<f64, String>;
+	fn ssim(&self, other: &Vec<u8>) -> Result<f64, String>;
+	fn diff_image(&self, other: &Vec<u8>, tolerance: u8) -> Result<Vec<u8>, String>;
+}
+
+impl Compare for Vec<u8> {
+	// True when both images have the same size and colors, however they are stored.
+	// A 32 bit image and a 24 bit copy, or an indexed image and its direct color version, are identical.
+	fn is_pixel_identical(&self, other: &Vec<u8>) -> bool {
+		match self.count_differences(other, 0) {
+			Ok(differences) => differences == 0,
+			Err(_) => false,
+		}
+	}
+
+	// Counts pixels where any channel differs by more than tolerance
+	fn count_differences(&self, other: &Vec<u8>, tolerance: u8) -> Result<u64, String> {
+		let (width, height) = check_comparable(self, other)?;
+		let mut differences = 0;
+		for y in 0..height {
+			for x in 0..width {
+				let point = Point { x, y };
+				if !color_within_tolerance(&self.get_pixel(&point)?, &other.get_pixel(&point)?, tolerance) {
+					differences += 1;
+				}
+			}
+		}
+		Ok(differences)
+	}
+
+	// Mean squared error over all channels of all pixels
+	fn mse(&self, other: &Vec<u8>) -> Result<f64, String> {
+		check_comparable(self, other)?;
+		let a = read_pixels(self)?;
+		let b = read_pixels(other)?;
+		let total: f64 = a.iter().zip(b.iter()).map(|(a, b)| (0..3).map(|i| (a[i] - b[i]).powi(2)).sum::<f64>()).sum();
+		Ok(total / (3 * a.len()).max(1) as f64)
+	}
+
+	// Peak signal to noise ratio in decibels, infinite for identical images
+	fn psnr(&self, other: &Vec<u8>) -> Result<f64, String> {
+		let mse = self.mse(other)?;
+		if mse == 0.0 {
+			return Ok(f64::INFINITY);
+		}
+		Ok(10.0 * (255.0 * 255.0 / mse).log10())
+	}
+
+	// Structural similarity index on luma, 1.0 for identical images
+	fn ssim(&self, other: &Vec<u8>) -> Result<f64, String> {
+		let (width, height) = check_comparable(self, other)?;
+		let weights = LumaWeights::Rec601.weights();
+		let luma = |pixel: &[f64; 3]| weights[0] * pixel[0] + weights[1] * pixel[1] + weights[2] * pixel[2];
+		let a: Vec<f64> = read_pixels(self)?.iter().map(luma).collect();
+		let b: Vec<f64> = read_pixels(other)?.iter().map(luma).collect();
+		if a.is_empty() {
+			return Ok(1.0);
+		}
+		Ok(ssim_luma(&a, &b, width, height))
+	}
+
+	// Returns a 24 bit image showing self faded to light gray, with pixels that differ by more than tolerance in red
+	fn diff_image(&self, other: &Vec<u8>, tolerance: u8) -> Result<Vec<u8>, String> {
+		let (width, height) = check_comparable(self, other)?;
+		let highlight = Rgb { r: 255, g: 0, b: 0 };
+		let mut diff = Vec::<u8>::new_bitmap(width, height, 24);
+		for y in 0..height {
+			for x in 0..width {
+				let point = Point { x, y };
+				let a = self.get_pixel(&point)?;
+				let b = other.get_pixel(&point)?;
+				if color_within_tolerance(&a, &b, tolerance) {
+					let faded = (LumaWeights::Rec601.luma(&a) / 4.0 + 191.0).round() as u8;
+					diff.draw_point(&point, &Rgb { r: faded, g: faded, b: faded });
+				} else {
+					diff.draw_point(&point, &highlight);
+				}
+			}
+		}
+		Ok(diff)
+	}
+}
+
+#[test]
+fn test_pixel_equality_and_differences() {
+	use crate::convert::*;
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(6, 5, 24);
+	bmp.draw_circle(&Point {x: 2, y: 2}, 2, &red);
+
+	// Same pixels stored differently
+	let wide = bmp.convert(32, &ConvertOptions::default()).unwrap();
+	assert_ne!(wide, bmp);
+	assert!(bmp.is_pixel_identical(&wide));
+	assert!(!bmp.is_pixel_identical(&Vec::<u8>::new_bitmap(6, 4, 24)));
+
+	let mut changed = bmp.clone();
+	changed.draw_point(&Point {x: 5, y: 4}, &Rgb {r: 3, g: 0, b: 0});
+	changed.draw_point(&Point {x: 5, y: 3}, &Rgb {r: 30, g: 0, b: 0});
+	assert_eq!(bmp.count_differences(&changed, 0).unwrap(), 2);
+	assert_eq!(bmp.count_differences(&changed, 5).unwrap(), 1);
+	assert!(bmp.count_differences(&Vec::<u8>::new_bitmap(5, 5, 24), 0).is_err());
+
+	let diff = bmp.diff_image(&changed, 5).unwrap();
+	assert_eq!(diff.get_pixel(&Point {x: 5, y: 3}).unwrap(), red);
+	assert_eq!(diff.get_pixel(&Point {x: 5, y: 4}).unwrap(), Rgb {r: 191, g: 191, b: 191});
+}
+
+#[test]
+fn test_error_metrics() {
+	use crate::filter::Convolve;
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(16, 16, 24);
+	bmp.draw_circle(&Point {x: 8, y: 8}, 5, &Rgb {r: 200, g: 150, b: 100});
+
+	assert_eq!(bmp.mse(&bmp).unwrap(), 0.0);
+	assert_eq!(bmp.psnr(&bmp).unwrap(), f64::INFINITY);
+	assert!((bmp.ssim(&bmp).unwrap() - 1.0).abs() < 1e-9);
+
+	// One channel of one pixel off by 48 gives an mse of 48^2 / 768
+	let mut changed = bmp.clone();
+	changed.draw_point(&Point {x: 0, y: 0}, &Rgb {r: 48, g: 0, b: 0});
+	assert!((bmp.mse(&changed).unwrap() - 3.0).abs() < 1e-9);
+	assert!((bmp.psnr(&changed).unwrap() - 43.36).abs() < 0.01);
+
+	// Blurring hurts structure more than a single pixel change
+	let blurred = bmp.gaussian_blur(2.0).unwrap();
+	let ssim_changed = bmp.ssim(&changed).unwrap();
+	let ssim_blurred = bmp.ssim(&blurred).unwrap();
+	assert!(ssim_changed < 1.0 && ssim_blurred < ssim_changed);
+}
@@ -0,0 +1,189 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Rect;
+use crate::Get;
+use crate::adjust::LumaWeights;
+use crate::color::Hsv;
+use crate::fill::Connectivity;
+use crate::signed::FloatPoint;
+
+// A connected group of foreground pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blob {
+	// Labels start at 1, 0 marks the background
+	pub label: u32,
+	// Number of pixels
+	pub area: u32,
+	pub bounds: Rect,
+	// Mean position of the pixels
+	pub centroid: FloatPoint,
+	// Number of pixel sides bordering the background or the edge of the image
+	pub perimeter: u32,
+}
+
+impl Blob {
+	// Opposite corners of the bounding box, as taken by draw_rectangle
+	pub fn corners(&self) -> (Point, Point) {
+		let bounds = self.bounds;
+		(Point { x: bounds.x, y: bounds.y }, Point { x: bounds.x + bounds.width - 1, y: bounds.y + bounds.height - 1 })
+	}
+}
+
+// The label of every pixel, stored row by row from y = 0, together with the blobs they belong to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Labeling {
+	pub width: u32,
+	pub height: u32,
+	pub labels: Vec<u32>,
+	// Ordered by label, in the order their first pixel is met scanning up from y = 0
+	pub blobs: Vec<Blob>,
+}
+
+impl Labeling {
+	// The label at point, 0 for background or points outside the image
+	pub fn label_at(&self, point: &Point) -> u32 {
+		if point.x >= self.width || point.y >= self.height {
+			return 0;
+		}
+		self.labels[point.y as usize * self.width as usize + point.x as usize]
+	}
+
+	// Returns a 24 bit image with the background black and every blob in its own color
+	pub fn colorize(&self) -> Vec<u8> {
+		let mut bmp = Vec::<u8>::new_bitmap(self.width, self.height, 24);
+		// Stepping the hue by the golden angle keeps neighbouring labels far apart in color
+		let colors: Vec<Rgb> = (0..self.blobs.len()).map(|index| Rgb::from(Hsv { h: index as f64 * 137.508, s: 0.75, v: 1.0 })).collect();
+		for y in 0..self.height {
+			for x in 0..self.width {
+				let label = self.labels[y as usize * self.width as usize + x as usize];
+				if label > 0 {
+					bmp.draw_point(&Point { x, y }, &colors[label as usize - 1]);
+				}
+			}
+		}
+		bmp
+	}
+}
+
+pub trait ConnectedComponents {
+	fn label_components(&self, connectivity: Connectivity) -> Result<Labeling, String>;
+}
+
+impl ConnectedComponents for Vec<u8> {
+	// Groups the foreground pixels, those with a luma above 127, into blobs.
+	// Meant for thresholded images, where white is foreground and black is background.
+	fn label_components(&self, connectivity: Connectivity) -> Result<Labeling, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let width = self.get_width();
+		let height = self.get_height();
+		let mut foreground = Vec::with_capacity(width as usize * height as usize);
+		for y in 0..height {
+			for x in 0..width {
+				foreground.push(LumaWeights::Rec601.luma(&self.get_pixel(&Point { x, y })?) > 127.0);
+			}
+		}
+
+		let neighbours: &[(i64, i64)] = match connectivity {
+			Connectivity::Four => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+			Connectivity::Eight => &[(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)],
+		};
+		let is_foreground = |x: i64, y: i64| x >= 0 && y >= 0 && x < width as i64 && y < height as i64 && foreground[(y * width as i64 + x) as usize];
+
+		let mut labels = vec![0; foreground.len()];
+		let mut blobs = Vec::new();
+		// An explicit stack instead of recursion, so large blobs cannot overflow the stack
+		let mut stack = Vec::new();
+		for start in 0..foreground.len() {
+			if !foreground[start] || labels[start] != 0 {
+				continue;
+			}
+			let label = blobs.len() as u32 + 1;
+			let (mut area, mut perimeter) = (0, 0);
+			let (mut sum_x, mut sum_y) = (0.0, 0.0);
+			let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+			labels[start] = label;
+			stack.push(start);
+			while let Some(index) = stack.pop() {
+				let x = (index % width as usize) as i64;
+				let y = (index / width as usize) as i64;
+				area += 1;
+				sum_x += x as f64;
+				sum_y += y as f64;
+				min_x = min_x.min(x as u32);
+				min_y = min_y.min(y as u32);
+				max_x = max_x.max(x as u32);
+				max_y = max_y.max(y as u32);
+				// The perimeter only counts sides, whichever connectivity joins the pixels
+				perimeter += neighbours[..4].iter().filter(|&&(dx, dy)| !is_foreground(x + dx, y + dy)).count() as u32;
+				for &(dx, dy) in neighbours {
+					if is_foreground(x + dx, y + dy) {
+						let neighbour = ((y + dy) * width as i64 + x + dx) as usize;
+						if labels[neighbour] == 0 {
+							labels[neighbour] = label;
+							stack.push(neighbour);
+						}
+					}
+				}
+			}
+			blobs.push(Blob {
+				label,
+				area,
+				bounds: Rect { x: min_x, y: min_y, width: max_x - min_x + 1, height: max_y - min_y + 1 },
+				centroid: FloatPoint { x: sum_x / area as f64, y: sum_y / area as f64 },
+				perimeter,
+			});
+		}
+		Ok(Labeling { width, height, labels, blobs })
+	}
+}
+
+#[test]
+fn test_label_components() {
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(10, 8, 24);
+	// A 3x2 block, and two pixels touching only at a corner
+	bmp.draw_rectangle(&Point {x: 1, y: 1}, &Point {x: 3, y: 2}, &white);
+	bmp.draw_point(&Point {x: 7, y: 5}, &white);
+	bmp.draw_point(&Point {x: 8, y: 6}, &white);
+
+	let four = bmp.label_components(Connectivity::Four).unwrap();
+	assert_eq!(four.blobs.len(), 3);
+	let block = four.blobs[0];
+	assert_eq!(block.area, 6);
+	assert_eq!(block.bounds, Rect {x: 1, y: 1, width: 3, height: 2});
+	assert_eq!(block.centroid, FloatPoint {x: 2.0, y: 1.5});
+	assert_eq!(block.perimeter, 10);
+	let (low, high) = block.corners();
+	assert_eq!((low.x, low.y, high.x, high.y), (1, 1, 3, 2));
+	assert_eq!(four.label_at(&Point {x: 8, y: 6}), 3);
+	assert_eq!(four.label_at(&Point {x: 0, y: 0}), 0);
+
+	let eight = bmp.label_components(Connectivity::Eight).unwrap();
+	assert_eq!(eight.blobs.len(), 2);
+	assert_eq!(eight.blobs[1].area, 2);
+	assert_eq!(eight.blobs[1].perimeter, 8);
+	assert_eq!(eight.blobs[1].bounds, Rect {x: 7, y: 5, width: 2, height: 2});
+}
+
+#[test]
+fn test_colorize_labels() {
+	use crate::indexed::*;
+	let black = Rgb {r: 0, g: 0, b: 0};
+	let mut mask:Vec<u8> = Vec::<u8>::new_indexed_bitmap(6, 3, 1, &[black, Rgb {r: 255, g: 255, b: 255}]).unwrap();
+	mask.set_pixel_index(&Point {x: 0, y: 0}, 1);
+	mask.set_pixel_index(&Point {x: 4, y: 2}, 1);
+	mask.set_pixel_index(&Point {x: 5, y: 2}, 1);
+
+	let labeling = mask.label_components(Connectivity::Eight).unwrap();
+	let colored = labeling.colorize();
+	assert_eq!(colored.get_bits_per_pixel(), 24);
+	assert_eq!(colored.get_pixel(&Point {x: 1, y: 1}).unwrap(), black);
+	let first = colored.get_pixel(&Point {x: 0, y: 0}).unwrap();
+	let second = colored.get_pixel(&Point {x: 4, y: 2}).unwrap();
+	assert_ne!(first, black);
+	assert_ne!(first, second);
+	assert_eq!(colored.get_pixel(&Point {x: 5, y: 2}).unwrap(), second);
+}
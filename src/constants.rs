@@ -3,6 +3,8 @@
 pub const HEADER_SIZE: u32 = 14;
 pub const INFOHEADER_SIZE:u32 = 40;
 pub const TOTAL_HEADER_SIZE:u32 = HEADER_SIZE + INFOHEADER_SIZE;
+// Each color table entry is stored as blue, green, red and a reserved byte
+pub const COLOR_TABLE_ENTRY_SIZE:u32 = 4;
 
 // Header offsets
 pub const HEADER_FILE_SIZE:usize = 2;
@@ -18,3 +20,10 @@ pub const HEADER_COMPRESSION_OFFSET:usize = 30;
 pub const HEADER_IMAGE_SIZE:usize = 34;
 pub const HEADER_COLORS_USED:usize = 46;
 pub const HEADER_IMPORTANT_COLORS:usize = 50;
+
+// Channel masks follow the 40 byte InfoHeader when the compression is BI_BITFIELDS
+pub const CHANNEL_MASKS_OFFSET:usize = 54;
+
+// Compression values
+pub const COMPRESSION_RGB:u32 = 0;
+pub const COMPRESSION_BITFIELDS:u32 = 3;
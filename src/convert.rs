@@ -0,0 +1,180 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::constants::*;
+use crate::quantize::*;
+
+// Scales the bits selected by mask to a full 0..255 channel
+pub(crate) fn unpack_channel(value: u32, mask: u32) -> u8 {
+	if mask == 0 {
+		return 0;
+	}
+	let max = mask >> mask.trailing_zeros();
+	let channel = (value & mask) >> mask.trailing_zeros();
+	((channel * 255 + max / 2) / max) as u8
+}
+
+// Scales a 0..255 channel down to the bits selected by mask
+pub(crate) fn pack_channel(value: u8, mask: u32) -> u32 {
+	if mask == 0 {
+		return 0;
+	}
+	let max = mask >> mask.trailing_zeros();
+	((value as u32 * max + 127) / 255) << mask.trailing_zeros()
+}
+
+// What happens to the alpha byte of a 32 bit source
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+	// The fourth byte is treated as unused padding
+	Ignore,
+	// Composites the image over a background color, using the fourth byte as opacity
+	Flatten(Rgb),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvertOptions {
+	// How the palette is built when converting to 1, 4 or 8 bits
+	pub method: QuantizeMethod,
+	// How colors missing from that palette are approximated
+	pub dither: Dither,
+	// Store 16 bit pixels as 5-6-5 with BI_BITFIELDS masks instead of the default 5-5-5
+	pub rgb565: bool,
+	pub alpha: AlphaMode,
+}
+
+impl Default for ConvertOptions {
+	fn default() -> Self {
+		ConvertOptions { method: QuantizeMethod::MedianCut, dither: Dither::FloydSteinberg, rgb565: false, alpha: AlphaMode::Ignore }
+	}
+}
+
+// Switches a new 16 bit image to BI_BITFIELDS with 5-6-5 masks, inserting the masks before the pixel array
+fn use_rgb565(bmp: &mut Vec<u8>) {
+	let masks: Vec<u8> = [0xF800u32, 0x07E0, 0x001F].iter().flat_map(|mask| mask.to_le_bytes()).collect();
+	bmp.splice(CHANNEL_MASKS_OFFSET..CHANNEL_MASKS_OFFSET, masks);
+	let pixel_array_offset = (CHANNEL_MASKS_OFFSET + 12) as u32;
+	bmp[HEADER_PIXEL_ARRAY_OFFSET..HEADER_PIXEL_ARRAY_OFFSET + 4].copy_from_slice(&pixel_array_offset.to_le_bytes());
+	bmp[HEADER_COMPRESSION_OFFSET..HEADER_COMPRESSION_OFFSET + 4].copy_from_slice(&COMPRESSION_BITFIELDS.to_le_bytes());
+	let file_size = bmp.len() as u32;
+	bmp[HEADER_FILE_SIZE..HEADER_FILE_SIZE + 4].copy_from_slice(&file_size.to_le_bytes());
+}
+
+// Reads the fourth byte of a 32 bit pixel
+fn get_alpha(bmp: &Vec<u8>, point: &Point) -> u8 {
+	let index = bmp.get_pixel_array_offset() + point.y as usize * bmp.get_row_size() + point.x as usize * 4 + 3;
+	bmp.get(index).copied().unwrap_or(255)
+}
+
+// Writes the fourth byte of a 32 bit pixel
+fn set_alpha(bmp: &mut Vec<u8>, point: &Point, alpha: u8) {
+	let index = bmp.get_pixel_array_offset() + point.y as usize * bmp.get_row_size() + point.x as usize * 4 + 3;
+	if let Some(byte) = bmp.get_mut(index) {
+		*byte = alpha;
+	}
+}
+
+pub trait Convert {
+	fn convert(&self, bpp: u16, options: &ConvertOptions) -> Result<Vec<u8>, String>;
+}
+
+impl Convert for Vec<u8> {
+	// Returns a copy stored with bpp bits per pixel, rewriting the header, color table and row padding.
+	// Going up in depth is lossless. Going down to 16 bits drops the low bits of each channel, and going down to
+	// 1, 4 or 8 bits builds a palette, which is lossless as long as the image has few enough colors.
+	// A 32 bit result is opaque unless the source is 32 bit as well, whose alpha is kept.
+	fn convert(&self, bpp: u16, options: &ConvertOptions) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		if !matches!(bpp, 1 | 4 | 8 | 16 | 24 | 32) {
+			return Err(format!("Cannot convert to {} bits per pixel, use 1, 4, 8, 16, 24 or 32", bpp));
+		}
+		let width = self.get_width();
+		let height = self.get_height();
+		let source_has_alpha = self.get_bits_per_pixel() == 32;
+
+		// Flattening resolves the alpha first, so the rest of the conversion only deals with opaque colors
+		if let (AlphaMode::Flatten(background), true) = (options.alpha, source_has_alpha) {
+			let mut flattened = Vec::<u8>::new_bitmap(width, height, 24);
+			for y in 0..height {
+				for x in 0..width {
+					let point = Point { x, y };
+					let color = self.get_pixel(&point)?;
+					let alpha = get_alpha(self, &point) as u32;
+					let mix = |front: u8, back: u8| ((front as u32 * alpha + back as u32 * (255 - alpha) + 127) / 255) as u8;
+					flattened.draw_point(&point, &Rgb { r: mix(color.r, background.r), g: mix(color.g, background.g), b: mix(color.b, background.b) });
+				}
+			}
+			let options = ConvertOptions { alpha: AlphaMode::Ignore, ..*options };
+			return flattened.convert(bpp, &options);
+		}
+
+		if bpp <= 8 {
+			return self.quantize(bpp, options.method, options.dither);
+		}
+
+		let mut converted = Vec::<u8>::new_bitmap(width, height, bpp);
+		if bpp == 16 && options.rgb565 {
+			use_rgb565(&mut converted);
+		}
+		for y in 0..height {
+			for x in 0..width {
+				let point = Point { x, y };
+				converted.draw_point(&point, &self.get_pixel(&point)?);
+				if bpp == 32 {
+					set_alpha(&mut converted, &point, if source_has_alpha { get_alpha(self, &point) } else { 255 });
+				}
+			}
+		}
+		Ok(converted)
+	}
+}
+
+#[test]
+fn test_convert_direct_color() {
+	let orange = Rgb {r: 255, g: 136, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(5, 3, 24);
+	bmp.draw_circle(&Point {x: 0, y: 0}, 2, &orange);
+	let options = ConvertOptions::default();
+
+	let wide = bmp.convert(32, &options).unwrap();
+	assert_eq!(wide.get_bits_per_pixel(), 32);
+	assert_eq!(wide.get_file_size(), wide.len() as u32);
+	assert_eq!(wide.convert(24, &options).unwrap(), bmp);
+
+	// 5-5-5 keeps 5 bits of every channel
+	let short = bmp.convert(16, &options).unwrap();
+	assert_eq!(short.get_row_size(), 12);
+	assert_eq!(short.get_padding_per_line(), 2);
+	assert_eq!(short.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 255, g: 140, b: 0});
+
+	let short = bmp.convert(16, &ConvertOptions { rgb565: true, ..options }).unwrap();
+	assert_eq!(short.get_compression(), COMPRESSION_BITFIELDS);
+	assert_eq!(short.get_channel_masks(), [0xF800, 0x07E0, 0x001F]);
+	assert_eq!(short.get_file_size(), short.len() as u32);
+	assert_eq!(short.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 255, g: 138, b: 0});
+	assert!(bmp.convert(12, &options).is_err());
+}
+
+#[test]
+fn test_convert_indexed_and_alpha() {
+	let orange = Rgb {r: 255, g: 136, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(5, 3, 24);
+	bmp.draw_circle(&Point {x: 0, y: 0}, 2, &orange);
+	let options = ConvertOptions::default();
+
+	// Two colors survive the trip through a 1 bit palette unchanged
+	let bits = bmp.convert(1, &options).unwrap();
+	assert_eq!(bits.get_palette().len(), 2);
+	assert_eq!(bits.convert(24, &options).unwrap(), bmp);
+	assert_eq!(bits.convert(4, &options).unwrap().convert(24, &options).unwrap(), bmp);
+
+	// Half transparent orange over white
+	let mut translucent = bmp.convert(32, &options).unwrap();
+	set_alpha(&mut translucent, &Point {x: 0, y: 0}, 128);
+	let flattened = translucent.convert(24, &ConvertOptions { alpha: AlphaMode::Flatten(Rgb {r: 255, g: 255, b: 255}), ..options }).unwrap();
+	assert_eq!(flattened.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 255, g: 195, b: 127});
+	assert_eq!(get_alpha(&translucent.convert(32, &options).unwrap(), &Point {x: 0, y: 0}), 128);
+}
@@ -0,0 +1,166 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Rect;
+use crate::Get;
+use crate::signed::SignedPoint;
+use crate::blit::*;
+use crate::paint::*;
+use crate::fill::color_within_tolerance;
+use crate::indexed::new_bitmap_like;
+
+// Where the original image is placed when the canvas grows or shrinks.
+// Top refers to the highest rows (largest y), which is the top of the image when viewed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+	TopLeft,
+	Top,
+	TopRight,
+	Left,
+	Center,
+	Right,
+	BottomLeft,
+	Bottom,
+	BottomRight,
+}
+
+pub trait Crop {
+	fn crop(&self, rect: &Rect) -> Result<Vec<u8>, String>;
+	fn resize_canvas(&self, width: u32, height: u32, anchor: Anchor, fill: &Rgb) -> Vec<u8>;
+	fn pad(&self, left: u32, right: u32, top: u32, bottom: u32, fill: &Rgb) -> Result<Vec<u8>, String>;
+	fn trim(&self, tolerance: u8) -> Result<Vec<u8>, String>;
+}
+
+impl Crop for Vec<u8> {
+	// Returns a new bitmap holding only the pixels inside rect
+	fn crop(&self, rect: &Rect) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let visible = rect.intersect(&self.clip_rect());
+		if visible != *rect || rect.is_empty() {
+			return Err(format!("Rectangle ({}, {}) {}x{} is not inside the image", rect.x, rect.y, rect.width, rect.height));
+		}
+		let mut cropped = new_bitmap_like(self, rect.width, rect.height);
+		cropped.blit(self, rect, &SignedPoint { x: 0, y: 0 }, &BlitOptions::default());
+		Ok(cropped)
+	}
+
+	// Returns a width x height bitmap with the image placed according to anchor.
+	// New areas are filled with fill and parts that no longer fit are cut off.
+	fn resize_canvas(&self, width: u32, height: u32, anchor: Anchor, fill: &Rgb) -> Vec<u8> {
+		let mut resized = new_bitmap_like(self, width, height);
+		if width > 0 && height > 0 {
+			resized.fill_rectangle(&Point { x: 0, y: 0 }, &Point { x: width - 1, y: height - 1 }, &Paint::Solid(*fill));
+		}
+
+		let extra_width = width as i64 - self.get_width() as i64;
+		let extra_height = height as i64 - self.get_height() as i64;
+		let x = match anchor {
+			Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => 0,
+			Anchor::Top | Anchor::Center | Anchor::Bottom => extra_width / 2,
+			Anchor::TopRight | Anchor::Right | Anchor::BottomRight => extra_width,
+		};
+		let y = match anchor {
+			Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => 0,
+			Anchor::Left | Anchor::Center | Anchor::Right => extra_height / 2,
+			Anchor::TopLeft | Anchor::Top | Anchor::TopRight => extra_height,
+		};
+		let destination = SignedPoint { x: x as i32, y: y as i32 };
+		resized.blit(self, &self.clip_rect(), &destination, &BlitOptions::default());
+		resized
+	}
+
+	// Adds a border of the given thickness on each side.
+	// Fails when the padded image would be too large for the width, height or file size fields of the header.
+	fn pad(&self, left: u32, right: u32, top: u32, bottom: u32, fill: &Rgb) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let too_large = || format!("Padding a {}x{} image by {}, {}, {}, {} does not fit in a bitmap", self.get_width(), self.get_height(), left, right, top, bottom);
+		let width = self.get_width().checked_add(left).and_then(|width| width.checked_add(right)).ok_or_else(too_large)?;
+		let height = self.get_height().checked_add(top).and_then(|height| height.checked_add(bottom)).ok_or_else(too_large)?;
+		// Width and height are stored as signed values, and the file size as an unsigned 32 bit value
+		let row_size = (width as u64 * self.get_bits_per_pixel() as u64).div_ceil(32) * 4;
+		let file_size = self.get_pixel_array_offset() as u64 + row_size * height as u64;
+		if width > i32::MAX as u32 || height > i32::MAX as u32 || file_size > u32::MAX as u64 {
+			return Err(too_large());
+		}
+		let mut padded = new_bitmap_like(self, width, height);
+		if width > 0 && height > 0 {
+			padded.fill_rectangle(&Point { x: 0, y: 0 }, &Point { x: width - 1, y: height - 1 }, &Paint::Solid(*fill));
+		}
+		padded.blit(self, &self.clip_rect(), &SignedPoint { x: left as i32, y: bottom as i32 }, &BlitOptions::default());
+		Ok(padded)
+	}
+
+	// Removes the border whose color matches the pixel at (0, 0) within tolerance
+	fn trim(&self, tolerance: u8) -> Result<Vec<u8>, String> {
+		let border = self.get_pixel(&Point { x: 0, y: 0 })?;
+		let width = self.get_width();
+		let height = self.get_height();
+
+		let mut min_x = u32::MAX;
+		let mut min_y = u32::MAX;
+		let mut max_x = 0;
+		let mut max_y = 0;
+		for y in 0..height {
+			for x in 0..width {
+				let pixel = self.get_pixel(&Point { x, y })?;
+				if !color_within_tolerance(&pixel, &border, tolerance) {
+					min_x = min_x.min(x);
+					min_y = min_y.min(y);
+					max_x = max_x.max(x);
+					max_y = max_y.max(y);
+				}
+			}
+		}
+		if min_x > max_x {
+			return Err("Image is a single uniform color, nothing is left after trimming".to_string());
+		}
+		self.crop(&Rect { x: min_x, y: min_y, width: max_x - min_x + 1, height: max_y - min_y + 1 })
+	}
+}
+
+#[test]
+fn test_crop_and_trim() {
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let black = Rgb {r: 0, g: 0, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(10, 8, 24);
+	bmp.draw_rectangle(&Point {x: 3, y: 2}, &Point {x: 7, y: 4}, &red);
+
+	let cropped = bmp.crop(&Rect {x: 3, y: 2, width: 5, height: 3}).unwrap();
+	assert_eq!(cropped.get_width(), 5);
+	assert_eq!(cropped.get_height(), 3);
+	assert_eq!(cropped.get_file_size(), cropped.len() as u32);
+	assert_eq!(cropped.get_image_size(), 48);
+	assert_eq!(cropped.get_pixel(&Point {x: 0, y: 0}).unwrap(), red);
+	assert_eq!(cropped.get_pixel(&Point {x: 2, y: 1}).unwrap(), black);
+	assert!(bmp.crop(&Rect {x: 8, y: 0, width: 5, height: 3}).is_err());
+
+	assert_eq!(bmp.trim(0).unwrap(), cropped);
+}
+
+#[test]
+fn test_pad_and_resize_canvas() {
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(2, 2, 24);
+	bmp.draw_circle(&Point {x: 0, y: 0}, 3, &red);
+
+	let padded = bmp.pad(1, 2, 3, 4, &white).unwrap();
+	assert_eq!(padded.get_width(), 5);
+	assert_eq!(padded.get_height(), 9);
+	assert_eq!(padded.get_pixel(&Point {x: 1, y: 4}).unwrap(), red);
+	assert_eq!(padded.get_pixel(&Point {x: 0, y: 4}).unwrap(), white);
+	assert_eq!(padded.get_pixel(&Point {x: 2, y: 6}).unwrap(), white);
+	// Sizes that overflow or do not fit the header are rejected before anything is allocated
+	assert!(bmp.pad(u32::MAX, 1, 0, 0, &white).is_err());
+	assert!(bmp.pad(0, 0, i32::MAX as u32, 0, &white).is_err());
+	assert!(bmp.pad(100000, 0, 100000, 0, &white).is_err());
+
+	let resized = bmp.resize_canvas(4, 4, Anchor::TopRight, &white);
+	assert_eq!(resized.get_pixel(&Point {x: 2, y: 2}).unwrap(), red);
+	assert_eq!(resized.get_pixel(&Point {x: 3, y: 3}).unwrap(), red);
+	assert_eq!(resized.get_pixel(&Point {x: 1, y: 1}).unwrap(), white);
+}
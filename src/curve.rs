@@ -0,0 +1,168 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::signed::*;
+
+// Maximum distance in pixels a flattened segment may deviate from the true curve
+pub const CURVE_TOLERANCE: f64 = 0.25;
+// Upper bound on subdivision depth, so degenerate control points cannot recurse forever
+const MAX_SUBDIVISION_DEPTH: u32 = 16;
+
+pub trait Curve {
+	fn draw_quadratic_bezier(&mut self, start: &Point, control: &Point, end: &Point, color: &Rgb);
+	fn draw_cubic_bezier(&mut self, start: &Point, control1: &Point, control2: &Point, end: &Point, color: &Rgb);
+	fn draw_catmull_rom(&mut self, points: &[Point], color: &Rgb);
+}
+
+impl<T: Bitmap> Curve for T {
+	fn draw_quadratic_bezier(&mut self, start: &Point, control: &Point, end: &Point, color: &Rgb) {
+		let flattened = flatten_quadratic(to_f64(start), to_f64(control), to_f64(end), CURVE_TOLERANCE);
+		self.draw_signed_polyline(&to_points(&flattened), color);
+	}
+
+	fn draw_cubic_bezier(&mut self, start: &Point, control1: &Point, control2: &Point, end: &Point, color: &Rgb) {
+		let flattened = flatten_cubic(to_f64(start), to_f64(control1), to_f64(control2), to_f64(end), CURVE_TOLERANCE);
+		self.draw_signed_polyline(&to_points(&flattened), color);
+	}
+
+	// Draws a smooth curve passing through every point in the list
+	fn draw_catmull_rom(&mut self, points: &[Point], color: &Rgb) {
+		let points: Vec<(f64, f64)> = points.iter().map(to_f64).collect();
+		let flattened = flatten_catmull_rom(&points, CURVE_TOLERANCE);
+		self.draw_signed_polyline(&to_points(&flattened), color);
+	}
+}
+
+// The center of the pixel, so flattening error cannot push a curve end into the neighbouring pixel
+fn to_f64(point: &Point) -> (f64, f64) {
+	(point.x as f64 + 0.5, point.y as f64 + 0.5)
+}
+
+// Maps flattened coordinates to the pixels containing them, like FloatPoint, dropping consecutive duplicates.
+// Signed points are used so curves that leave the image are clipped rather than bent onto its edge.
+pub(crate) fn to_points(flattened: &[(f64, f64)]) -> Vec<SignedPoint> {
+	let mut points: Vec<SignedPoint> = Vec::with_capacity(flattened.len());
+	for &(x, y) in flattened {
+		let point = SignedPoint { x: x.floor() as i32, y: y.floor() as i32 };
+		if let Some(last) = points.last() {
+			if last.x == point.x && last.y == point.y {
+				continue;
+			}
+		}
+		points.push(point);
+	}
+	points
+}
+
+fn midpoint(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+	((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+// Distance from point p to the line through a and b
+fn distance_to_line(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+	let dx = b.0 - a.0;
+	let dy = b.1 - a.1;
+	let length = (dx * dx + dy * dy).sqrt();
+	if length == 0.0 {
+		return ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt();
+	}
+	((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / length
+}
+
+// Approximates a quadratic Bézier curve with line segments. The returned list includes both endpoints.
+pub fn flatten_quadratic(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), tolerance: f64) -> Vec<(f64, f64)> {
+	let mut output = vec![p0];
+	subdivide_quadratic(p0, p1, p2, tolerance, 0, &mut output);
+	output
+}
+
+fn subdivide_quadratic(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), tolerance: f64, depth: u32, output: &mut Vec<(f64, f64)>) {
+	// The curve is flat enough once the control point lies close to the chord
+	if depth >= MAX_SUBDIVISION_DEPTH || distance_to_line(p1, p0, p2) <= tolerance {
+		output.push(p2);
+		return;
+	}
+	// De Casteljau split at t = 0.5
+	let p01 = midpoint(p0, p1);
+	let p12 = midpoint(p1, p2);
+	let mid = midpoint(p01, p12);
+	subdivide_quadratic(p0, p01, mid, tolerance, depth + 1, output);
+	subdivide_quadratic(mid, p12, p2, tolerance, depth + 1, output);
+}
+
+// Approximates a cubic Bézier curve with line segments. The returned list includes both endpoints.
+pub fn flatten_cubic(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), p3: (f64, f64), tolerance: f64) -> Vec<(f64, f64)> {
+	let mut output = vec![p0];
+	subdivide_cubic(p0, p1, p2, p3, tolerance, 0, &mut output);
+	output
+}
+
+fn subdivide_cubic(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), p3: (f64, f64), tolerance: f64, depth: u32, output: &mut Vec<(f64, f64)>) {
+	let flatness = distance_to_line(p1, p0, p3).max(distance_to_line(p2, p0, p3));
+	if depth >= MAX_SUBDIVISION_DEPTH || flatness <= tolerance {
+		output.push(p3);
+		return;
+	}
+	// De Casteljau split at t = 0.5
+	let p01 = midpoint(p0, p1);
+	let p12 = midpoint(p1, p2);
+	let p23 = midpoint(p2, p3);
+	let p012 = midpoint(p01, p12);
+	let p123 = midpoint(p12, p23);
+	let mid = midpoint(p012, p123);
+	subdivide_cubic(p0, p01, p012, mid, tolerance, depth + 1, output);
+	subdivide_cubic(mid, p123, p23, p3, tolerance, depth + 1, output);
+}
+
+// Approximates a uniform Catmull-Rom spline through all points with line segments.
+// Each span is converted to the equivalent cubic Bézier and flattened; the end points are duplicated so the curve reaches them.
+pub fn flatten_catmull_rom(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+	if points.len() < 3 {
+		return points.to_vec();
+	}
+	let mut output = vec![points[0]];
+	for i in 0..points.len() - 1 {
+		let p0 = points[i.saturating_sub(1)];
+		let p1 = points[i];
+		let p2 = points[i + 1];
+		let p3 = points[(i + 2).min(points.len() - 1)];
+		let control1 = (p1.0 + (p2.0 - p0.0) / 6.0, p1.1 + (p2.1 - p0.1) / 6.0);
+		let control2 = (p2.0 - (p3.0 - p1.0) / 6.0, p2.1 - (p3.1 - p1.1) / 6.0);
+		subdivide_cubic(p1, control1, control2, p2, tolerance, 0, &mut output);
+	}
+	output
+}
+
+#[test]
+fn test_draw_polyline_and_curves() {
+	use crate::Get;
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(40, 40, 24);
+	let color = Rgb {r: 255, g: 0, b: 0};
+
+	// An empty slice must not panic
+	bmp.draw_polyline(&[], &color);
+	bmp.draw_polygon(&[], &color);
+
+	// Curves pass through their end points
+	let start = Point {x: 2, y: 2};
+	let end = Point {x: 30, y: 5};
+	bmp.draw_quadratic_bezier(&start, &Point {x: 16, y: 35}, &end, &color);
+	assert_eq!(bmp.get_pixel(&start).unwrap(), color);
+	assert_eq!(bmp.get_pixel(&end).unwrap(), color);
+
+	let points = [Point {x: 5, y: 20}, Point {x: 15, y: 30}, Point {x: 25, y: 20}, Point {x: 35, y: 30}];
+	bmp.draw_catmull_rom(&points, &color);
+	for point in points.iter() {
+		assert_eq!(bmp.get_pixel(point).unwrap(), color);
+	}
+}
+
+#[test]
+fn test_flatten_cubic_is_adaptive() {
+	// A straight cubic needs a single segment, a curved one needs more
+	let straight = flatten_cubic((0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0), CURVE_TOLERANCE);
+	assert_eq!(straight.len(), 2);
+	let curved = flatten_cubic((0.0, 0.0), (0.0, 50.0), (50.0, 50.0), (50.0, 0.0), CURVE_TOLERANCE);
+	assert!(curved.len() > 8);
+	assert_eq!(*curved.last().unwrap(), (50.0, 0.0));
+}
@@ -0,0 +1,187 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::clip::Canvas;
+
+// Which neighbours of a pixel count as connected to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connectivity {
+	// Left, right, up and down
+	Four,
+	// Also includes the diagonals
+	Eight,
+}
+
+pub trait FloodFill {
+	fn flood_fill(&mut self, start: &Point, color: &Rgb, tolerance: u8, connectivity: Connectivity);
+}
+
+// True when every channel of a differs from b by at most tolerance
+pub fn color_within_tolerance(a: &Rgb, b: &Rgb, tolerance: u8) -> bool {
+	a.r.abs_diff(b.r) <= tolerance && a.g.abs_diff(b.g) <= tolerance && a.b.abs_diff(b.b) <= tolerance
+}
+
+// Finds the region of color within tolerance of the pixel at start, as (y, x_start, x_end) spans.
+// Uses an explicit stack of scanline seeds instead of recursion, so large regions cannot overflow the stack.
+#[allow(clippy::ptr_arg)]
+fn flood_spans(bmp: &Vec<u8>, start: &Point, tolerance: u8, connectivity: Connectivity) -> Vec<(u32, u32, u32)> {
+	let mut spans = Vec::new();
+	let target = match bmp.get_pixel(start) {
+		Ok(pixel) => pixel,
+		Err(_) => return spans,
+	};
+	let width = bmp.get_width();
+	let height = bmp.get_height();
+
+	// Track visited pixels, since seeds can be pushed more than once
+	let mut visited = vec![false; width as usize * height as usize];
+	let matches = |visited: &Vec<bool>, x: u32, y: u32| -> bool {
+		if visited[y as usize * width as usize + x as usize] {
+			return false;
+		}
+		match bmp.get_pixel(&Point { x, y }) {
+			Ok(pixel) => color_within_tolerance(&pixel, &target, tolerance),
+			Err(_) => false,
+		}
+	};
+
+	let mut stack: Vec<(u32, u32)> = vec![(start.x, start.y)];
+	while let Some((x, y)) = stack.pop() {
+		if !matches(&visited, x, y) {
+			continue;
+		}
+
+		// Extend the span as far as possible in both directions
+		let mut left = x;
+		while left > 0 && matches(&visited, left - 1, y) {
+			left -= 1;
+		}
+		let mut right = x;
+		while right + 1 < width && matches(&visited, right + 1, y) {
+			right += 1;
+		}
+		for span_x in left..=right {
+			visited[y as usize * width as usize + span_x as usize] = true;
+		}
+		spans.push((y, left, right));
+
+		// Seed the rows above and below, one seed per run of matching pixels
+		let (scan_left, scan_right) = match connectivity {
+			Connectivity::Four => (left, right),
+			Connectivity::Eight => (left.saturating_sub(1), (right + 1).min(width - 1)),
+		};
+		let mut neighbour_rows: Vec<u32> = Vec::with_capacity(2);
+		if y > 0 {
+			neighbour_rows.push(y - 1);
+		}
+		if y + 1 < height {
+			neighbour_rows.push(y + 1);
+		}
+		for neighbour_y in neighbour_rows {
+			let mut in_run = false;
+			for scan_x in scan_left..=scan_right {
+				if matches(&visited, scan_x, neighbour_y) {
+					if !in_run {
+						stack.push((scan_x, neighbour_y));
+						in_run = true;
+					}
+				} else {
+					in_run = false;
+				}
+			}
+		}
+	}
+	spans
+}
+
+impl FloodFill for Vec<u8> {
+	// Replaces the region of similar color containing start with color
+	fn flood_fill(&mut self, start: &Point, color: &Rgb, tolerance: u8, connectivity: Connectivity) {
+		for (y, x_start, x_end) in flood_spans(self, start, tolerance, connectivity) {
+			for x in x_start..=x_end {
+				self.draw_point(&Point { x, y }, color);
+			}
+		}
+	}
+}
+
+impl FloodFill for Canvas<'_> {
+	// The region is found on the whole image, and only the part inside the clip rectangle and mask is painted
+	fn flood_fill(&mut self, start: &Point, color: &Rgb, tolerance: u8, connectivity: Connectivity) {
+		let clip = self.clip_rect();
+		for (y, x_start, x_end) in flood_spans(self.bitmap(), start, tolerance, connectivity) {
+			if clip.is_empty() || y < clip.y || y - clip.y >= clip.height {
+				continue;
+			}
+			let x_start = x_start.max(clip.x);
+			let x_end = x_end.min(clip.x + clip.width - 1);
+			for x in x_start..=x_end {
+				self.draw_point(&Point { x, y }, color);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_flood_fill_connectivity() {
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(20, 20, 24);
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let black = Rgb {r: 0, g: 0, b: 0};
+
+	// A diagonal wall splits the image for 4-connectivity but not for 8-connectivity
+	bmp.draw_line(&Point {x: 0, y: 19}, &Point {x: 19, y: 0}, &white);
+	bmp.flood_fill(&Point {x: 0, y: 0}, &red, 0, Connectivity::Four);
+	assert_eq!(bmp.get_pixel(&Point {x: 2, y: 2}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 19, y: 19}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 10, y: 9}).unwrap(), white);
+
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(20, 20, 24);
+	bmp.draw_line(&Point {x: 0, y: 19}, &Point {x: 19, y: 0}, &white);
+	bmp.flood_fill(&Point {x: 0, y: 0}, &red, 0, Connectivity::Eight);
+	assert_eq!(bmp.get_pixel(&Point {x: 19, y: 19}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 10, y: 9}).unwrap(), white);
+}
+
+#[test]
+fn test_flood_fill_tolerance() {
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(10, 10, 24);
+	let dark = Rgb {r: 10, g: 10, b: 10};
+	let blue = Rgb {r: 0, g: 0, b: 255};
+	bmp.draw_rectangle(&Point {x: 0, y: 0}, &Point {x: 9, y: 9}, &dark);
+
+	// The slightly lighter border is only included once the tolerance allows it
+	bmp.flood_fill(&Point {x: 5, y: 5}, &blue, 5, Connectivity::Four);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 0}).unwrap(), dark);
+	bmp.flood_fill(&Point {x: 5, y: 5}, &blue, 255, Connectivity::Four);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 0}).unwrap(), blue);
+}
+
+#[test]
+fn test_flood_fill_on_canvas() {
+	use crate::Rect;
+	use crate::clip::*;
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(12, 12, 24);
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let black = Rgb {r: 0, g: 0, b: 0};
+	let mut mask = ClipMask::new(12, 12);
+	for x in 0..12 {
+		mask.set(x, 5, true);
+		mask.set(x, 6, true);
+	}
+	mask.set(3, 6, false);
+
+	let mut canvas = Canvas::new(&mut bmp);
+	canvas.set_clip_rect(&Rect {x: 2, y: 0, width: 6, height: 12});
+	canvas.set_clip_mask(Some(mask));
+	canvas.flood_fill(&Point {x: 0, y: 0}, &red, 0, Connectivity::Four);
+
+	// Only the masked rows inside the clip rectangle are painted, though the start point is outside both
+	assert_eq!(bmp.get_pixel(&Point {x: 2, y: 5}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 7, y: 6}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 6}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 1, y: 5}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 8, y: 5}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 4, y: 4}).unwrap(), black);
+}
@@ -0,0 +1,267 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Get;
+use crate::resample::*;
+
+// What the kernel sees when it reaches past the edge of the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EdgeMode {
+	// Repeat the nearest edge pixel
+	Clamp,
+	// Continue from the opposite side
+	Wrap,
+	// Reflect the image at its edge
+	Mirror,
+	// Use a fixed color
+	Constant(Rgb),
+}
+
+// A rectangular grid of weights, stored row by row. The first row is applied to the lowest (smallest y) row under the kernel.
+// Weights are applied as written, without flipping the kernel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+	width: usize,
+	height: usize,
+	weights: Vec<f64>,
+}
+
+impl Kernel {
+	// Width and height must be odd so the kernel has a center pixel
+	pub fn new(width: usize, height: usize, weights: &[f64]) -> Result<Kernel, String> {
+		if width % 2 != 1 || height % 2 != 1 {
+			return Err(format!("Kernel size {}x{} must be odd in both directions", width, height));
+		}
+		if weights.len() != width * height {
+			return Err(format!("Kernel of {}x{} needs {} weights, got {}", width, height, width * height, weights.len()));
+		}
+		Ok(Kernel { width, height, weights: weights.to_vec() })
+	}
+
+	// Returns a copy whose weights add up to 1. Kernels summing to 0, like edge detectors, are returned unchanged.
+	pub fn normalized(&self) -> Kernel {
+		let total: f64 = self.weights.iter().sum();
+		if total.abs() < 1e-12 {
+			return self.clone();
+		}
+		Kernel { width: self.width, height: self.height, weights: self.weights.iter().map(|weight| weight / total).collect() }
+	}
+}
+
+// Maps a possibly out of range index onto 0..size, or None when the constant color should be used
+fn edge_index(index: i64, size: i64, edge: EdgeMode) -> Option<usize> {
+	if index >= 0 && index < size {
+		return Some(index as usize);
+	}
+	match edge {
+		EdgeMode::Clamp => Some(index.clamp(0, size - 1) as usize),
+		EdgeMode::Wrap => Some(index.rem_euclid(size) as usize),
+		EdgeMode::Mirror => {
+			let folded = index.rem_euclid(2 * size);
+			Some(if folded >= size { 2 * size - folded - 1 } else { folded } as usize)
+		}
+		EdgeMode::Constant(_) => None,
+	}
+}
+
+// Applies a kernel to a row-major pixel list
+fn convolve_pixels(pixels: &[[f64; 3]], width: u32, height: u32, kernel: &Kernel, edge: EdgeMode) -> Vec<[f64; 3]> {
+	let constant = match edge {
+		EdgeMode::Constant(color) => [color.r as f64, color.g as f64, color.b as f64],
+		_ => [0.0; 3],
+	};
+	let half_width = (kernel.width / 2) as i64;
+	let half_height = (kernel.height / 2) as i64;
+	let mut output = Vec::with_capacity(pixels.len());
+	for y in 0..height as i64 {
+		for x in 0..width as i64 {
+			let mut sum = [0.0; 3];
+			for ky in 0..kernel.height as i64 {
+				let source_y = edge_index(y + ky - half_height, height as i64, edge);
+				for kx in 0..kernel.width as i64 {
+					let weight = kernel.weights[(ky * kernel.width as i64 + kx) as usize];
+					if weight == 0.0 {
+						continue;
+					}
+					let source_x = edge_index(x + kx - half_width, width as i64, edge);
+					let pixel = match (source_x, source_y) {
+						(Some(sx), Some(sy)) => pixels[sy * width as usize + sx],
+						_ => constant,
+					};
+					for channel in 0..3 {
+						sum[channel] += pixel[channel] * weight;
+					}
+				}
+			}
+			output.push(sum);
+		}
+	}
+	output
+}
+
+// Applies a one dimensional kernel horizontally and then vertically, which is much cheaper than the equivalent square kernel
+pub(crate) fn convolve_separable(pixels: &[[f64; 3]], width: u32, height: u32, weights: &[f64], edge: EdgeMode) -> Vec<[f64; 3]> {
+	let horizontal = Kernel { width: weights.len(), height: 1, weights: weights.to_vec() };
+	let vertical = Kernel { width: 1, height: weights.len(), weights: weights.to_vec() };
+	let pass = convolve_pixels(pixels, width, height, &horizontal, edge);
+	convolve_pixels(&pass, width, height, &vertical, edge)
+}
+
+// Normalized one dimensional Gaussian covering three standard deviations on each side
+pub(crate) fn gaussian_weights(sigma: f64) -> Vec<f64> {
+	let radius = (3.0 * sigma).ceil().max(1.0) as i64;
+	let weights: Vec<f64> = (-radius..=radius).map(|i| (-((i * i) as f64) / (2.0 * sigma * sigma)).exp()).collect();
+	let total: f64 = weights.iter().sum();
+	weights.iter().map(|weight| weight / total).collect()
+}
+
+const SOBEL_X: [f64; 9] = [-1.0, 0.0, 1.0, -2.0, 0.0, 2.0, -1.0, 0.0, 1.0];
+const SOBEL_Y: [f64; 9] = [-1.0, -2.0, -1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 1.0];
+const PREWITT_X: [f64; 9] = [-1.0, 0.0, 1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0];
+const PREWITT_Y: [f64; 9] = [-1.0, -1.0, -1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+const LAPLACIAN: [f64; 9] = [0.0, 1.0, 0.0, 1.0, -4.0, 1.0, 0.0, 1.0, 0.0];
+const EMBOSS: [f64; 9] = [-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0];
+
+pub trait Convolve {
+	fn convolve(&self, kernel: &Kernel, normalize: bool, edge: EdgeMode) -> Result<Vec<u8>, String>;
+	fn box_blur(&self, radius: u32) -> Result<Vec<u8>, String>;
+	fn gaussian_blur(&self, sigma: f64) -> Result<Vec<u8>, String>;
+	fn unsharp_mask(&self, sigma: f64, amount: f64, threshold: u8) -> Result<Vec<u8>, String>;
+	fn sobel(&self) -> Result<Vec<u8>, String>;
+	fn prewitt(&self) -> Result<Vec<u8>, String>;
+	fn laplacian(&self) -> Result<Vec<u8>, String>;
+	fn emboss(&self) -> Result<Vec<u8>, String>;
+}
+
+// Reads the pixels along with the image size, rejecting buffers that are not bitmaps
+fn source_pixels(bmp: &Vec<u8>) -> Result<(Vec<[f64; 3]>, u32, u32), String> {
+	if !bmp.has_file_signature() {
+		return Err("Invalid file signature. Not a Bitmap.".to_string());
+	}
+	Ok((read_pixels(bmp)?, bmp.get_width(), bmp.get_height()))
+}
+
+// Combines the horizontal and vertical gradient responses into their magnitude
+fn gradient_magnitude(bmp: &Vec<u8>, kernel_x: &[f64; 9], kernel_y: &[f64; 9]) -> Result<Vec<u8>, String> {
+	let (pixels, width, height) = source_pixels(bmp)?;
+	let gx = convolve_pixels(&pixels, width, height, &Kernel::new(3, 3, kernel_x)?, EdgeMode::Clamp);
+	let gy = convolve_pixels(&pixels, width, height, &Kernel::new(3, 3, kernel_y)?, EdgeMode::Clamp);
+	let magnitude: Vec<[f64; 3]> = gx.iter().zip(gy.iter()).map(|(a, b)| {
+		[(a[0] * a[0] + b[0] * b[0]).sqrt(), (a[1] * a[1] + b[1] * b[1]).sqrt(), (a[2] * a[2] + b[2] * b[2]).sqrt()]
+	}).collect();
+	Ok(write_pixels(&magnitude, width, height, bmp))
+}
+
+impl Convolve for Vec<u8> {
+	// Returns a new bitmap where every pixel is the weighted sum of its neighbourhood
+	fn convolve(&self, kernel: &Kernel, normalize: bool, edge: EdgeMode) -> Result<Vec<u8>, String> {
+		let kernel = if normalize { kernel.normalized() } else { kernel.clone() };
+		let (pixels, width, height) = source_pixels(self)?;
+		let output = convolve_pixels(&pixels, width, height, &kernel, edge);
+		Ok(write_pixels(&output, width, height, self))
+	}
+
+	// Averages each (2 * radius + 1) square neighbourhood
+	fn box_blur(&self, radius: u32) -> Result<Vec<u8>, String> {
+		let size = 2 * radius as usize + 1;
+		let weights = vec![1.0 / size as f64; size];
+		let (pixels, width, height) = source_pixels(self)?;
+		let output = convolve_separable(&pixels, width, height, &weights, EdgeMode::Clamp);
+		Ok(write_pixels(&output, width, height, self))
+	}
+
+	fn gaussian_blur(&self, sigma: f64) -> Result<Vec<u8>, String> {
+		if sigma <= 0.0 {
+			return Err(format!("Gaussian sigma must be positive, got {}", sigma));
+		}
+		let (pixels, width, height) = source_pixels(self)?;
+		let output = convolve_separable(&pixels, width, height, &gaussian_weights(sigma), EdgeMode::Clamp);
+		Ok(write_pixels(&output, width, height, self))
+	}
+
+	// Sharpens by adding amount times the difference from a Gaussian blur.
+	// Channels that differ from the blur by no more than threshold are left alone, so flat areas keep their noise level.
+	fn unsharp_mask(&self, sigma: f64, amount: f64, threshold: u8) -> Result<Vec<u8>, String> {
+		if sigma <= 0.0 {
+			return Err(format!("Gaussian sigma must be positive, got {}", sigma));
+		}
+		let (pixels, width, height) = source_pixels(self)?;
+		let blurred = convolve_separable(&pixels, width, height, &gaussian_weights(sigma), EdgeMode::Clamp);
+		let output: Vec<[f64; 3]> = pixels.iter().zip(blurred.iter()).map(|(original, blur)| {
+			let mut sharpened = *original;
+			for channel in 0..3 {
+				let difference = original[channel] - blur[channel];
+				if difference.abs() > threshold as f64 {
+					sharpened[channel] += amount * difference;
+				}
+			}
+			sharpened
+		}).collect();
+		Ok(write_pixels(&output, width, height, self))
+	}
+
+	fn sobel(&self) -> Result<Vec<u8>, String> {
+		gradient_magnitude(self, &SOBEL_X, &SOBEL_Y)
+	}
+
+	fn prewitt(&self) -> Result<Vec<u8>, String> {
+		gradient_magnitude(self, &PREWITT_X, &PREWITT_Y)
+	}
+
+	// Edges in every direction; the response is made positive so both sides of an edge show up
+	fn laplacian(&self) -> Result<Vec<u8>, String> {
+		let (pixels, width, height) = source_pixels(self)?;
+		let output = convolve_pixels(&pixels, width, height, &Kernel::new(3, 3, &LAPLACIAN)?, EdgeMode::Clamp);
+		let output: Vec<[f64; 3]> = output.iter().map(|pixel| [pixel[0].abs(), pixel[1].abs(), pixel[2].abs()]).collect();
+		Ok(write_pixels(&output, width, height, self))
+	}
+
+	fn emboss(&self) -> Result<Vec<u8>, String> {
+		self.convolve(&Kernel::new(3, 3, &EMBOSS)?, false, EdgeMode::Clamp)
+	}
+}
+
+#[test]
+fn test_convolve_edge_modes() {
+	use crate::Point;
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let black = Rgb {r: 0, g: 0, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(3, 1, 24);
+	bmp.draw_point(&Point {x: 2, y: 0}, &white);
+
+	// Averaging three pixels horizontally; only the left edge pixel depends on the edge mode
+	let kernel = Kernel::new(3, 1, &[1.0, 1.0, 1.0]).unwrap();
+	let clamp = bmp.convolve(&kernel, true, EdgeMode::Clamp).unwrap();
+	assert_eq!(clamp.get_pixel(&Point {x: 0, y: 0}).unwrap(), black);
+	assert_eq!(clamp.get_pixel(&Point {x: 2, y: 0}).unwrap(), Rgb {r: 170, g: 170, b: 170});
+	let wrap = bmp.convolve(&kernel, true, EdgeMode::Wrap).unwrap();
+	assert_eq!(wrap.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 85, g: 85, b: 85});
+	let mirror = bmp.convolve(&kernel, true, EdgeMode::Mirror).unwrap();
+	assert_eq!(mirror.get_pixel(&Point {x: 0, y: 0}).unwrap(), black);
+	let constant = bmp.convolve(&kernel, true, EdgeMode::Constant(white)).unwrap();
+	assert_eq!(constant.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 85, g: 85, b: 85});
+
+	assert!(Kernel::new(2, 3, &[0.0; 6]).is_err());
+	assert!(Kernel::new(3, 3, &[0.0; 6]).is_err());
+}
+
+#[test]
+fn test_blur_and_edges() {
+	use crate::Point;
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(9, 9, 24);
+	bmp.draw_circle(&Point {x: 4, y: 4}, 20, &red);
+
+	// A uniform image is unchanged by blurring and has no edges
+	assert_eq!(bmp.gaussian_blur(1.5).unwrap(), bmp);
+	assert_eq!(bmp.box_blur(2).unwrap(), bmp);
+	assert_eq!(bmp.unsharp_mask(1.0, 1.5, 0).unwrap(), bmp);
+	let flat = Vec::<u8>::new_bitmap(9, 9, 24);
+	assert_eq!(bmp.sobel().unwrap(), flat);
+	assert_eq!(bmp.laplacian().unwrap(), flat);
+
+	// A vertical edge is found by the gradient filters
+	bmp.draw_line(&Point {x: 5, y: 0}, &Point {x: 5, y: 8}, &Rgb {r: 0, g: 0, b: 0});
+	let edges = bmp.prewitt().unwrap();
+	assert_eq!(edges.get_pixel(&Point {x: 0, y: 4}).unwrap(), Rgb {r: 0, g: 0, b: 0});
+	assert_ne!(edges.get_pixel(&Point {x: 4, y: 4}).unwrap(), Rgb {r: 0, g: 0, b: 0});
+}
@@ -17,6 +17,7 @@
  *
  **/
 
+#[allow(clippy::empty_line_after_doc_comments)]
 pub const FONT_WIDTH: usize = 8;
 pub const FONT_HEIGHT: usize = 8;
 
@@ -2,6 +2,7 @@ use crate::Bitmap;
 use crate::Rgb;
 use crate::Point;
 use crate::constants::*;
+use crate::convert::unpack_channel;
 
 pub trait Get {
 	fn get_pixel_array_offset(&self) -> usize;
@@ -17,16 +18,38 @@ pub trait Get {
 	fn get_important_colors(&self) -> u32;
 	fn get_padding_size(&self) -> u32;
 	fn get_padding_per_line(&self) -> u8;
+	fn get_row_size(&self) -> usize;
+	fn get_palette(&self) -> Vec<Rgb>;
+	fn get_pixel_index(&self, point: &Point) -> Result<u8, String>;
+	fn get_channel_masks(&self) -> [u32; 3];
 	fn get_pixel(&self, point: &Point) -> Result<Rgb, String>;
 }
 
 impl Get for Vec<u8> {
+        #[allow(clippy::unnecessary_cast, clippy::needless_return)]
         fn get_pixel(&self, point: &Point) -> Result<Rgb, String> {
                 // Ensure the point is within the bounds of the bitmap
                 if !self.point_exists(point) {
 			return Err(format!("Point ({}, {}) is out of bounds", point.x, point.y));
                 }
 
+		// Indexed pixels hold a position in the color table
+		if self.get_bits_per_pixel() <= 8 {
+			let index = self.get_pixel_index(point)?;
+			return self.get_palette().get(index as usize).copied().ok_or_else(|| format!("Color index {} is not in the palette", index));
+		}
+
+		// 16 bit pixels pack the channels according to the channel masks
+		if self.get_bits_per_pixel() == 16 {
+			let index = self.get_pixel_array_offset() + point.y as usize * self.get_row_size() + point.x as usize * 2;
+			if index + 1 >= self.len() {
+				return Err(format!("Point ({}, {}) is out of bounds", point.x, point.y));
+			}
+			let value = u16::from_le_bytes([self[index], self[index + 1]]) as u32;
+			let [red, green, blue] = self.get_channel_masks();
+			return Ok(Rgb {r: unpack_channel(value, red), g: unpack_channel(value, green), b: unpack_channel(value, blue)});
+		}
+
                 // Convert dimensions to usize for consistent indexing
                 let width = self.get_width() as usize;
                 let bytes_per_pixel = (self.get_bits_per_pixel() as usize) / 8;
@@ -53,18 +76,16 @@ impl Get for Vec<u8> {
 
 	// Returns bytes of padding per scan line
 	fn get_padding_per_line(&self) -> u8 {
-                // Calculate the width of the scan line in bytes
-                let mut padded_width: u32 = self.get_width() * (self.get_bits_per_pixel() as u32 / 8);
-                // Compute the remainder when divided by 4
-                let remainder = padded_width % 4;
-
-                // If there's no remainder, it's already a multiple of 4
-                if remainder != 0 {
-                        // Add padding to make it a multiple of 4
-                        padded_width += 4 - remainder;
-                }
+                // Calculate the width of the scan line in bytes, rounding partly used bytes of 1 and 4 bit images up
+                let used_width = (self.get_width() as usize * self.get_bits_per_pixel() as usize).div_ceil(8);
                 // Return the padding (which is the difference between padded width and original width)
-                (padded_width - (self.get_width() * (self.get_bits_per_pixel() as u32 / 8))) as u8
+                (self.get_row_size() - used_width) as u8
+	}
+
+	// Returns bytes per scan line, including padding
+	fn get_row_size(&self) -> usize {
+		let row_bits = self.get_width() as usize * self.get_bits_per_pixel() as usize;
+		row_bits.div_ceil(32) * 4
 	}
 
 	// Returns total bytes spent on padding
@@ -73,8 +94,62 @@ impl Get for Vec<u8> {
 	}
 
 
+	// Returns the color table of an indexed image, or an empty list for direct color images.
+	// A colors used count of 0 means the full 2^bpp entries are present.
+	fn get_palette(&self) -> Vec<Rgb> {
+		let bits_per_pixel = self.get_bits_per_pixel() as u32;
+		let entries = match self.get_colors_used() {
+			0 if bits_per_pixel <= 8 => 1 << bits_per_pixel,
+			0 => 0,
+			used => used,
+		};
+		let table = HEADER_SIZE as usize + self.get_size_of_info_header() as usize;
+		(0..entries as usize)
+			.map(|i| table + i * COLOR_TABLE_ENTRY_SIZE as usize)
+			.take_while(|&entry| entry + 3 <= self.len())
+			.map(|entry| Rgb { r: self[entry + 2], g: self[entry + 1], b: self[entry] })
+			.collect()
+	}
+
+	// Returns the color table position stored for a pixel of a 1, 4 or 8 bit image
+	fn get_pixel_index(&self, point: &Point) -> Result<u8, String> {
+		if !self.point_exists(point) {
+			return Err(format!("Point ({}, {}) is out of bounds", point.x, point.y));
+		}
+		let bits_per_pixel = self.get_bits_per_pixel() as usize;
+		if !matches!(bits_per_pixel, 1 | 4 | 8) {
+			return Err(format!("A {} bit image has no color indices", bits_per_pixel));
+		}
+		// Pixels are packed from the most significant bit of each byte
+		let bit = point.x as usize * bits_per_pixel;
+		let index = self.get_pixel_array_offset() + point.y as usize * self.get_row_size() + bit / 8;
+		if index >= self.len() {
+			return Err(format!("Point ({}, {}) is out of bounds", point.x, point.y));
+		}
+		let shift = 8 - bits_per_pixel - bit % 8;
+		Ok((self[index] >> shift) & ((1u16 << bits_per_pixel) - 1) as u8)
+	}
+
+	// Returns the red, green and blue bit masks of a 16 or 32 bit image.
+	// Without BI_BITFIELDS compression these are the fixed 5-5-5 and 8-8-8 layouts.
+	fn get_channel_masks(&self) -> [u32; 3] {
+		if self.get_compression() == COMPRESSION_BITFIELDS && self.len() >= CHANNEL_MASKS_OFFSET + 12 {
+			let mask = |i: usize| {
+				let byte_slice = &self[CHANNEL_MASKS_OFFSET + 4 * i..CHANNEL_MASKS_OFFSET + 4 * i + 4];
+				u32::from_le_bytes([byte_slice[0], byte_slice[1], byte_slice[2], byte_slice[3]])
+			};
+			return [mask(0), mask(1), mask(2)];
+		}
+		if self.get_bits_per_pixel() == 16 {
+			[0x7C00, 0x03E0, 0x001F]
+		} else {
+			[0xFF0000, 0x00FF00, 0x0000FF]
+		}
+	}
+
 	fn get_pixel_array_offset(&self) -> usize {
-		return self[HEADER_PIXEL_ARRAY_OFFSET] as usize; 
+		let byte_slice = &self[HEADER_PIXEL_ARRAY_OFFSET..HEADER_PIXEL_ARRAY_OFFSET+4];
+		u32::from_le_bytes([byte_slice[0], byte_slice[1], byte_slice[2], byte_slice[3]]) as usize
 	}
 	fn get_width(&self) -> u32 {
 		let byte_slice = &self[HEADER_WIDTH_OFFSET..HEADER_WIDTH_OFFSET+4];
@@ -84,6 +159,7 @@ impl Get for Vec<u8> {
 		let byte_slice = &self[HEADER_HEIGHT_OFFSET..HEADER_HEIGHT_OFFSET+4];
 		u32::from_le_bytes([byte_slice[0], byte_slice[1], byte_slice[2], byte_slice[3]])
         }
+	#[allow(clippy::needless_return)]
 	fn get_bits_per_pixel(&self) -> u8 {
 		return self[HEADER_BITS_PER_PIXEL];
 	}
@@ -0,0 +1,163 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::constants::*;
+
+// Squared euclidean distance between two colors
+pub(crate) fn color_distance(a: &Rgb, b: &Rgb) -> u32 {
+	let dr = a.r as i32 - b.r as i32;
+	let dg = a.g as i32 - b.g as i32;
+	let db = a.b as i32 - b.b as i32;
+	(dr * dr + dg * dg + db * db) as u32
+}
+
+// Position of the palette entry closest to color, 0 for an empty palette
+pub fn nearest_palette_index(palette: &[Rgb], color: &Rgb) -> u8 {
+	palette.iter().enumerate().min_by_key(|(_, entry)| color_distance(entry, color)).map(|(index, _)| index as u8).unwrap_or(0)
+}
+
+// A blank width x height image with the depth of bmp. Indexed images keep their color table,
+// so pixels taken from bmp can be stored without being forced onto the default grayscale palette.
+pub(crate) fn new_bitmap_like(bmp: &Vec<u8>, width: u32, height: u32) -> Vec<u8> {
+	let bpp = bmp.get_bits_per_pixel() as u16;
+	if bmp.is_indexed() {
+		if let Ok(result) = Vec::<u8>::new_indexed_bitmap(width, height, bpp, &bmp.get_palette()) {
+			return result;
+		}
+	}
+	Vec::<u8>::new_bitmap(width, height, bpp)
+}
+
+pub trait Indexed {
+	fn new_indexed_bitmap(width: u32, height: u32, bpp: u16, palette: &[Rgb]) -> Result<Vec<u8>, String>;
+	fn is_indexed(&self) -> bool;
+	fn set_palette(&mut self, palette: &[Rgb]) -> Result<(), String>;
+	fn set_pixel_index(&mut self, point: &Point, index: u8);
+}
+
+impl Indexed for Vec<u8> {
+	// Creates a 1, 4 or 8 bit image using palette as its color table. Every pixel starts as palette entry 0.
+	fn new_indexed_bitmap(width: u32, height: u32, bpp: u16, palette: &[Rgb]) -> Result<Vec<u8>, String> {
+		let mut bmp = Vec::<u8>::new_bitmap(width, height, bpp);
+		bmp.set_palette(palette)?;
+		Ok(bmp)
+	}
+
+	fn is_indexed(&self) -> bool {
+		self.has_file_signature() && matches!(self.get_bits_per_pixel(), 1 | 4 | 8)
+	}
+
+	// Replaces the color table, growing or shrinking it as needed. Pixel indices are kept as they are.
+	fn set_palette(&mut self, palette: &[Rgb]) -> Result<(), String> {
+		if !self.is_indexed() {
+			return Err(format!("A {} bit image has no color table", self.get_bits_per_pixel()));
+		}
+		let max_entries = 1usize << self.get_bits_per_pixel();
+		if palette.is_empty() || palette.len() > max_entries {
+			return Err(format!("A {} bit image needs 1 to {} palette entries, got {}", self.get_bits_per_pixel(), max_entries, palette.len()));
+		}
+		let table_start = HEADER_SIZE as usize + self.get_size_of_info_header() as usize;
+		let pixel_array_offset = self.get_pixel_array_offset();
+		let table: Vec<u8> = palette.iter().flat_map(|color| [color.b, color.g, color.r, 0]).collect();
+		self.splice(table_start..pixel_array_offset, table);
+
+		let pixel_array_offset = (table_start + palette.len() * COLOR_TABLE_ENTRY_SIZE as usize) as u32;
+		self[HEADER_PIXEL_ARRAY_OFFSET..HEADER_PIXEL_ARRAY_OFFSET + 4].copy_from_slice(&pixel_array_offset.to_le_bytes());
+		self[HEADER_COLORS_USED..HEADER_COLORS_USED + 4].copy_from_slice(&(palette.len() as u32).to_le_bytes());
+		let file_size = self.len() as u32;
+		self[HEADER_FILE_SIZE..HEADER_FILE_SIZE + 4].copy_from_slice(&file_size.to_le_bytes());
+		Ok(())
+	}
+
+	// Stores a color table position for one pixel. Points outside the image and direct color images are ignored.
+	fn set_pixel_index(&mut self, point: &Point, index: u8) {
+		if !self.point_exists(point) || !self.is_indexed() {
+			return;
+		}
+		let bits_per_pixel = self.get_bits_per_pixel() as usize;
+		// Pixels are packed from the most significant bit of each byte
+		let bit = point.x as usize * bits_per_pixel;
+		let offset = self.get_pixel_array_offset() + point.y as usize * self.get_row_size() + bit / 8;
+		if offset >= self.len() {
+			eprintln!("Index out of bounds: {}", offset);
+			return;
+		}
+		let shift = 8 - bits_per_pixel - bit % 8;
+		let mask = (((1u16 << bits_per_pixel) - 1) << shift) as u8;
+		self[offset] = (self[offset] & !mask) | ((index << shift) & mask);
+	}
+}
+
+#[test]
+fn test_indexed_pixels() {
+	let palette = [Rgb {r: 0, g: 0, b: 128}, Rgb {r: 255, g: 200, b: 0}, Rgb {r: 10, g: 200, b: 10}];
+	for bpp in [1, 4, 8] {
+		let mut bmp = Vec::<u8>::new_indexed_bitmap(13, 3, bpp, &palette[..palette.len().min(1 << bpp)]).unwrap();
+		assert_eq!(bmp.get_file_size(), bmp.len() as u32);
+		assert_eq!(bmp.get_pixel_array_offset() as u32 + bmp.get_row_size() as u32 * 3, bmp.len() as u32);
+		assert_eq!(bmp.get_palette()[1], palette[1]);
+
+		bmp.draw_point(&Point {x: 12, y: 2}, &Rgb {r: 250, g: 190, b: 20});
+		bmp.set_pixel_index(&Point {x: 11, y: 2}, 1);
+		assert_eq!(bmp.get_pixel_index(&Point {x: 12, y: 2}).unwrap(), 1);
+		assert_eq!(bmp.get_pixel(&Point {x: 11, y: 2}).unwrap(), palette[1]);
+		assert_eq!(bmp.get_pixel(&Point {x: 10, y: 2}).unwrap(), palette[0]);
+		assert_eq!(bmp.get_pixel(&Point {x: 12, y: 1}).unwrap(), palette[0]);
+	}
+	assert!(Vec::<u8>::new_indexed_bitmap(2, 2, 1, &palette).is_err());
+	assert!(Vec::<u8>::new_indexed_bitmap(2, 2, 24, &palette).is_err());
+}
+
+#[test]
+fn test_default_grayscale_palette() {
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(5, 2, 4);
+	assert_eq!(bmp.get_palette().len(), 16);
+	assert_eq!(bmp.get_colors_used(), 16);
+	assert_eq!(bmp.get_row_size(), 4);
+	assert_eq!(bmp.get_padding_per_line(), 1);
+	bmp.draw_point(&Point {x: 4, y: 1}, &Rgb {r: 255, g: 255, b: 255});
+	assert_eq!(bmp.get_pixel(&Point {x: 4, y: 1}).unwrap(), Rgb {r: 255, g: 255, b: 255});
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 1}).unwrap(), Rgb {r: 0, g: 0, b: 0});
+}
+
+#[test]
+fn test_geometry_keeps_palette() {
+	use crate::Rect;
+	use crate::crop::*;
+	use crate::transform::*;
+	use crate::resample::*;
+	let palette = [Rgb {r: 0, g: 0, b: 128}, Rgb {r: 255, g: 0, b: 0}, Rgb {r: 10, g: 200, b: 10}];
+	let mut bmp = Vec::<u8>::new_indexed_bitmap(6, 4, 4, &palette).unwrap();
+	for y in 0..4 {
+		bmp.set_pixel_index(&Point {x: 0, y}, 1);
+		bmp.set_pixel_index(&Point {x: 5, y}, 2);
+	}
+
+	let cropped = bmp.crop(&Rect {x: 0, y: 1, width: 3, height: 2}).unwrap();
+	assert_eq!(cropped.get_palette(), palette.to_vec());
+	assert_eq!(cropped.get_pixel(&Point {x: 0, y: 0}).unwrap(), palette[1]);
+	assert_eq!(cropped.get_pixel(&Point {x: 1, y: 0}).unwrap(), palette[0]);
+
+	let turned = bmp.rotate_90();
+	assert_eq!(turned.get_bits_per_pixel(), 4);
+	assert_eq!(turned.get_palette(), palette.to_vec());
+	assert_eq!(turned.get_pixel(&Point {x: 3, y: 0}).unwrap(), palette[1]);
+	assert_eq!(turned.rotate_270(), bmp);
+
+	let resized = bmp.resize(12, 8, ResizeFilter::Bilinear).unwrap();
+	assert_eq!(resized.get_palette(), palette.to_vec());
+	assert_eq!(resized.get_pixel(&Point {x: 0, y: 3}).unwrap(), palette[1]);
+	assert_eq!(resized.get_pixel(&Point {x: 11, y: 3}).unwrap(), palette[2]);
+}
+
+#[test]
+fn test_only_bmp_depths_get_a_palette() {
+	for bpp in [0, 2, 3, 5, 6, 7] {
+		let bmp:Vec<u8> = Vec::<u8>::new_bitmap(3, 2, bpp);
+		assert!(!bmp.is_indexed());
+		assert!(bmp.get_palette().is_empty());
+		assert_eq!(bmp.get_colors_used(), 0);
+		assert_eq!(bmp.get_pixel_array_offset(), TOTAL_HEADER_SIZE as usize);
+	}
+}
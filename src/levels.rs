@@ -0,0 +1,237 @@
+use crate::Bitmap;
+use crate::Get;
+use crate::adjust::LumaWeights;
+use crate::resample::*;
+use crate::stats::*;
+
+// Finds the values below which low_percent and above which high_percent of the pixels fall
+fn clip_points(counts: &[u32; 256], low_percent: f64, high_percent: f64) -> (u8, u8) {
+	let total: u64 = counts.iter().map(|&count| count as u64).sum();
+	let low_limit = total as f64 * low_percent / 100.0;
+	let high_limit = total as f64 * high_percent / 100.0;
+	let mut low = 0;
+	let mut seen = 0u64;
+	for (value, &count) in counts.iter().enumerate() {
+		seen += count as u64;
+		if seen as f64 > low_limit {
+			low = value;
+			break;
+		}
+	}
+	let mut high = 255;
+	seen = 0;
+	for (value, &count) in counts.iter().enumerate().rev() {
+		seen += count as u64;
+		if seen as f64 > high_limit {
+			high = value;
+			break;
+		}
+	}
+	(low as u8, high.max(low) as u8)
+}
+
+// Maps low..high linearly onto 0..255
+fn stretch_table(low: u8, high: u8) -> [f64; 256] {
+	let mut table = [0.0; 256];
+	for (value, entry) in table.iter_mut().enumerate() {
+		*entry = if high == low {
+			value as f64
+		} else {
+			((value as f64 - low as f64) * 255.0 / (high as f64 - low as f64)).clamp(0.0, 255.0)
+		};
+	}
+	table
+}
+
+// Maps each value to its position in the cumulative distribution, so the output values are spread evenly
+fn equalization_table(counts: &[u32; 256]) -> [f64; 256] {
+	let total: u64 = counts.iter().map(|&count| count as u64).sum();
+	let first = counts.iter().map(|&count| count as u64).find(|&count| count > 0).unwrap_or(0);
+	let mut table = [0.0; 256];
+	let mut cumulative = 0u64;
+	for (value, entry) in table.iter_mut().enumerate() {
+		cumulative += counts[value] as u64;
+		*entry = if total == first {
+			value as f64
+		} else {
+			(cumulative.saturating_sub(first)) as f64 * 255.0 / (total - first) as f64
+		};
+	}
+	table
+}
+
+// Cuts every bin at limit and spreads the excess evenly over all bins, which is what keeps CLAHE from amplifying noise
+fn clip_histogram(counts: &mut [f64; 256], limit: f64) {
+	let mut excess = 0.0;
+	for count in counts.iter_mut() {
+		if *count > limit {
+			excess += *count - limit;
+			*count = limit;
+		}
+	}
+	for count in counts.iter_mut() {
+		*count += excess / 256.0;
+	}
+}
+
+fn luma(pixel: &[f64; 3]) -> f64 {
+	let weights = LumaWeights::Rec601.weights();
+	weights[0] * pixel[0] + weights[1] * pixel[1] + weights[2] * pixel[2]
+}
+
+// Moves every channel by how much the brightness changed, so gray stays gray and colors keep their hue
+fn shift_luma(pixel: &[f64; 3], old_luma: f64, new_luma: f64) -> [f64; 3] {
+	let shift = new_luma - old_luma;
+	[pixel[0] + shift, pixel[1] + shift, pixel[2] + shift]
+}
+
+pub trait Levels {
+	fn auto_levels(&self, low_clip_percent: f64, high_clip_percent: f64, per_channel: bool) -> Result<Vec<u8>, String>;
+	fn equalize_histogram(&self) -> Result<Vec<u8>, String>;
+	fn clahe(&self, tiles_x: u32, tiles_y: u32, clip_limit: f64) -> Result<Vec<u8>, String>;
+}
+
+impl Levels for Vec<u8> {
+	// Stretches the range so the darkest low_clip_percent of pixels become black and the brightest high_clip_percent become white.
+	// With per_channel each channel is stretched on its own, which also removes color casts; otherwise all share the luma range.
+	fn auto_levels(&self, low_clip_percent: f64, high_clip_percent: f64, per_channel: bool) -> Result<Vec<u8>, String> {
+		let histogram = self.histogram()?;
+		let tables = if per_channel {
+			[Channel::Red, Channel::Green, Channel::Blue].map(|channel| {
+				let (low, high) = clip_points(histogram.channel(channel), low_clip_percent, high_clip_percent);
+				stretch_table(low, high)
+			})
+		} else {
+			let (low, high) = clip_points(&histogram.luma, low_clip_percent, high_clip_percent);
+			[stretch_table(low, high); 3]
+		};
+		let pixels: Vec<[f64; 3]> = read_pixels(self)?.iter().map(|pixel| {
+			[tables[0][pixel[0] as usize], tables[1][pixel[1] as usize], tables[2][pixel[2] as usize]]
+		}).collect();
+		Ok(write_pixels(&pixels, self.get_width(), self.get_height(), self))
+	}
+
+	// Spreads the luma values evenly over the whole range
+	fn equalize_histogram(&self) -> Result<Vec<u8>, String> {
+		let histogram = self.histogram()?;
+		let table = equalization_table(&histogram.luma);
+		let pixels: Vec<[f64; 3]> = read_pixels(self)?.iter().map(|pixel| {
+			let old_luma = luma(pixel);
+			shift_luma(pixel, old_luma, table[old_luma.round() as usize])
+		}).collect();
+		Ok(write_pixels(&pixels, self.get_width(), self.get_height(), self))
+	}
+
+	// Contrast limited adaptive histogram equalization. The image is split into tiles_x by tiles_y tiles, each equalized
+	// with its histogram clipped at clip_limit times the average bin height, and the tile mappings are blended bilinearly.
+	fn clahe(&self, tiles_x: u32, tiles_y: u32, clip_limit: f64) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let width = self.get_width();
+		let height = self.get_height();
+		if tiles_x == 0 || tiles_y == 0 || tiles_x > width || tiles_y > height {
+			return Err(format!("Cannot split a {}x{} image into {}x{} tiles", width, height, tiles_x, tiles_y));
+		}
+		if clip_limit < 1.0 {
+			return Err(format!("Clip limit must be at least 1.0, got {}", clip_limit));
+		}
+		let pixels = read_pixels(self)?;
+		let lumas: Vec<f64> = pixels.iter().map(luma).collect();
+
+		// Tile i covers columns i * width / tiles_x up to (i + 1) * width / tiles_x
+		let tile_start = |index: u32, tiles: u32, size: u32| (index as u64 * size as u64 / tiles as u64) as u32;
+		let mut tables = Vec::with_capacity((tiles_x * tiles_y) as usize);
+		for tile_y in 0..tiles_y {
+			for tile_x in 0..tiles_x {
+				let (x0, x1) = (tile_start(tile_x, tiles_x, width), tile_start(tile_x + 1, tiles_x, width));
+				let (y0, y1) = (tile_start(tile_y, tiles_y, height), tile_start(tile_y + 1, tiles_y, height));
+				let mut counts = [0.0; 256];
+				for y in y0..y1 {
+					for x in x0..x1 {
+						counts[lumas[(y * width + x) as usize].round() as usize] += 1.0;
+					}
+				}
+				let area = ((x1 - x0) * (y1 - y0)) as f64;
+				clip_histogram(&mut counts, clip_limit * area / 256.0);
+				let mut table = [0.0; 256];
+				let mut cumulative = 0.0;
+				for (value, entry) in table.iter_mut().enumerate() {
+					cumulative += counts[value];
+					*entry = (cumulative * 255.0 / area).clamp(0.0, 255.0);
+				}
+				tables.push(table);
+			}
+		}
+
+		// Each pixel blends the mappings of the four tiles whose centers surround it
+		let tile_width = width as f64 / tiles_x as f64;
+		let tile_height = height as f64 / tiles_y as f64;
+		let neighbours = |position: f64, tiles: u32| -> (usize, usize, f64) {
+			let grid = (position - 0.5).clamp(0.0, (tiles - 1) as f64);
+			let first = grid.floor() as usize;
+			let second = (first + 1).min(tiles as usize - 1);
+			(first, second, grid - first as f64)
+		};
+		let mut output = Vec::with_capacity(pixels.len());
+		for y in 0..height {
+			let (below, above, ty) = neighbours((y as f64 + 0.5) / tile_height, tiles_y);
+			for x in 0..width {
+				let (left, right, tx) = neighbours((x as f64 + 0.5) / tile_width, tiles_x);
+				let index = (y * width + x) as usize;
+				let value = lumas[index].round() as usize;
+				let map = |row: usize, column: usize| tables[row * tiles_x as usize + column][value];
+				let lower = map(below, left) * (1.0 - tx) + map(below, right) * tx;
+				let upper = map(above, left) * (1.0 - tx) + map(above, right) * tx;
+				output.push(shift_luma(&pixels[index], lumas[index], lower * (1.0 - ty) + upper * ty));
+			}
+		}
+		Ok(write_pixels(&output, width, height, self))
+	}
+}
+
+#[test]
+fn test_auto_levels_and_equalization() {
+	use crate::Rgb;
+	use crate::Point;
+	// A low contrast gradient from 100 to 150
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(51, 2, 24);
+	for x in 0..51 {
+		let value = 100 + x as u8;
+		bmp.draw_line(&Point {x, y: 0}, &Point {x, y: 1}, &Rgb {r: value, g: value, b: value});
+	}
+
+	let stretched = bmp.auto_levels(0.0, 0.0, false).unwrap();
+	assert_eq!(stretched.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 0, g: 0, b: 0});
+	assert_eq!(stretched.get_pixel(&Point {x: 50, y: 1}).unwrap(), Rgb {r: 255, g: 255, b: 255});
+	let stats = stretched.statistics().unwrap();
+	assert!((stats.red.mean - 127.5).abs() < 0.5);
+
+	// Clipping 10% at each end saturates the outer columns
+	let clipped = bmp.auto_levels(10.0, 10.0, true).unwrap();
+	assert_eq!(clipped.get_pixel(&Point {x: 3, y: 0}).unwrap(), Rgb {r: 0, g: 0, b: 0});
+	assert_eq!(clipped.get_pixel(&Point {x: 47, y: 0}).unwrap(), Rgb {r: 255, g: 255, b: 255});
+
+	let equalized = bmp.equalize_histogram().unwrap();
+	assert_eq!(equalized.get_pixel(&Point {x: 50, y: 0}).unwrap(), Rgb {r: 255, g: 255, b: 255});
+	assert_eq!(equalized.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 0, g: 0, b: 0});
+}
+
+#[test]
+fn test_clahe() {
+	use crate::Rgb;
+	use crate::Point;
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(16, 16, 24);
+	for x in 0..16 {
+		let value = 120 + (x % 2) as u8 * 10;
+		bmp.draw_line(&Point {x, y: 0}, &Point {x, y: 15}, &Rgb {r: value, g: value, b: value});
+	}
+	let before = bmp.statistics().unwrap();
+	let enhanced = bmp.clahe(2, 2, 4.0).unwrap();
+	let after = enhanced.statistics().unwrap();
+	assert!(after.luma.stddev > before.luma.stddev);
+	assert_eq!((enhanced.get_width(), enhanced.get_height()), (16, 16));
+
+	assert!(bmp.clahe(0, 2, 4.0).is_err());
+	assert!(bmp.clahe(2, 2, 0.5).is_err());
+}
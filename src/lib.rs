@@ -7,12 +7,120 @@ pub use crate::constants::*;
 mod font;
 use crate::font::*;
 
+mod curve;
+pub use curve::*;
+
+mod path;
+pub use path::*;
+
+mod fill;
+pub use fill::*;
+
+mod signed;
+pub use signed::*;
+use crate::signed::fill_disk;
+
+mod clip;
+pub use clip::*;
+use crate::clip::draw_clipped_line;
+
+mod blend;
+pub use blend::*;
+
+mod paint;
+pub use paint::*;
+
+mod blit;
+pub use blit::*;
+
+mod crop;
+pub use crop::*;
+
+mod resample;
+pub use resample::*;
+
+mod transform;
+pub use transform::*;
+
+mod warp;
+pub use warp::*;
+
+mod filter;
+pub use filter::*;
+
+mod adjust;
+pub use adjust::*;
+
+mod color;
+pub use color::*;
+
+mod stats;
+pub use stats::*;
+
+mod levels;
+pub use levels::*;
+
+mod indexed;
+pub use indexed::*;
+
+mod quantize;
+pub use quantize::*;
+
+mod convert;
+pub use convert::*;
+use crate::convert::pack_channel;
+
+mod threshold;
+pub use threshold::*;
+
+mod compare;
+pub use compare::*;
+
+mod morphology;
+pub use morphology::*;
+
+mod components;
+pub use components::*;
+
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+#[cfg(any(test, feature = "testing"))]
+pub use testing::*;
+
 pub struct Point {
 	pub x: u32,
 	pub y: u32
 }
 
-#[derive(Debug, PartialEq)]
+// An axis aligned area of width x height pixels whose lowest corner is (x, y)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32
+}
+
+impl Rect {
+	pub fn is_empty(&self) -> bool {
+		self.width == 0 || self.height == 0
+	}
+
+	pub fn contains(&self, x: i64, y: i64) -> bool {
+		x >= self.x as i64 && y >= self.y as i64 && x < self.x as i64 + self.width as i64 && y < self.y as i64 + self.height as i64
+	}
+
+	// The area covered by both rectangles, which may be empty
+	pub fn intersect(&self, other: &Rect) -> Rect {
+		let x = self.x.max(other.x);
+		let y = self.y.max(other.y);
+		let right = (self.x as u64 + self.width as u64).min(other.x as u64 + other.width as u64);
+		let top = (self.y as u64 + self.height as u64).min(other.y as u64 + other.height as u64);
+		Rect { x, y, width: right.saturating_sub(x as u64) as u32, height: top.saturating_sub(y as u64) as u32 }
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct Rgb {
 	pub r: u8,
 	pub g: u8,
@@ -23,26 +131,130 @@ pub trait Bitmap {
 	fn new_bitmap(width: u32, height: u32, bpp: u16) -> Vec<u8>;
 	fn point_exists(&self, point: &Point) -> bool;
 	fn draw_point(&mut self, start:&Point, color: &Rgb);
-	fn draw_circle(&mut self, center: &Point, radius: u32, color: &Rgb);
-	fn draw_line(&mut self, start: &Point, end: &Point, color: &Rgb);
-	fn draw_rectangle(&mut self, point1: &Point, point2: &Point, color: &Rgb);
-	fn draw_char(&mut self, char_index: usize, position: &Point, color:&Rgb);
-	fn draw_string(&mut self, string: &str, position: &Point, color: &Rgb);
-	fn draw_polygon(&mut self, points: &[Point], color: &Rgb);
 	fn has_file_signature(&self) -> bool;
+
+	// The area drawing is limited to. Implementations should return their bounds, or less when clipping.
+	// The default places no limit and relies on draw_point to discard points outside the image.
+	fn clip_rect(&self) -> Rect {
+		Rect { x: 0, y: 0, width: u32::MAX, height: u32::MAX }
+	}
+
+	// The shapes below are built from draw_point, so they honour whatever clipping the implementation applies there
+
+	fn draw_circle(&mut self, center: &Point, radius: u32, color: &Rgb) {
+		// Only the part of the bounding box inside the clip rectangle is visited
+		fill_disk(self, center.x as f64, center.y as f64, radius as f64, color);
+	}
+
+	fn draw_line(&mut self, start: &Point, end: &Point, color: &Rgb) {
+		// Coordinates are widened to i64 so values above i32::MAX cannot wrap
+		draw_clipped_line(self, start.x as i64, start.y as i64, end.x as i64, end.y as i64, color);
+	}
+
+	// Input two opposite corners of the rectangle
+	#[allow(clippy::needless_borrow)]
+	fn draw_rectangle(&mut self, point1: &Point, point2: &Point, color: &Rgb) {
+		let point3 = Point {x:point1.x , y:point2.y};
+		let point4 = Point {x: point2.x, y: point1.y};
+		self.draw_line(&point1, &point3, &color);
+		self.draw_line(&point3, &point2, &color);
+		self.draw_line(&point2, &point4, &color);
+		self.draw_line(&point4, &point1, &color);
+	}
+
+	#[allow(clippy::unnecessary_cast)]
+	fn draw_char(&mut self, char_index: usize, position: &Point, color: &Rgb) {
+		// Ensure char_index is within bounds of FONT_BITMAP
+		if char_index >= FONT_BITMAP.len() {
+			return; // or handle the error as appropriate
+		}
+
+		// Get the bitmap for the character
+		let bitmap = &FONT_BITMAP[char_index];
+
+		// Iterate over rows of the character's bitmap from bottom to top
+		for row in (0..FONT_HEIGHT).rev() {
+			// Iterate over columns of the character's bitmap from left to right
+			for col in 0..FONT_WIDTH {
+				// Calculate the index into the bitmap
+				let bit_index = (FONT_HEIGHT - 1 - row) * FONT_WIDTH + col;
+				let byte_index = bit_index / 8;
+				let bit_position = 7 - (bit_index % 8);
+	
+				// Ensure the byte index is within bounds of the bitmap array
+				if byte_index < bitmap.len() {
+					// Create a mask for the bit position
+					let mask = 1 << bit_position;
+	
+					// Check if the specific bit is set
+					if (bitmap[byte_index] & mask) != 0 {
+						// Calculate the correct x and y coordinates for the point
+						let x = position.x as u32 + (FONT_WIDTH - 1 - col) as u32;
+						let y = position.y as u32 + row as u32;
+	
+						// Draw the point
+						let point = Point { x, y };
+						self.draw_point(&point, color);
+					}
+				}
+			}
+		}
+	}
+
+
+	fn draw_string(&mut self, string: &str, position: &Point, color: &Rgb) {
+		let mut x_offset = position.x; // Start at the initial x position
+		// Iterate over each character in the string
+		for char in string.chars() {
+			// Find the index of the character in the font bitmap
+			let char_index = char as usize - 32;
+			// Draw the character at the current position
+			self.draw_char(char_index, &Point { x: x_offset, y: position.y }, color);
+			// Move the x offset by the width of the character plus any spacing
+			x_offset += FONT_WIDTH as u32;
+		}
+	}
+
+	fn draw_polygon(&mut self, points: &[Point], color: &Rgb) {
+		if points.is_empty() {
+			return;
+		}
+		self.draw_polyline(points, color);
+		self.draw_line(&points[0], &points[points.len()-1], color);
+	}
+
+	// Draws connected line segments without closing the shape
+	fn draw_polyline(&mut self, points: &[Point], color: &Rgb) {
+		match points.len() {
+			0 => {}
+			1 => self.draw_point(&points[0], color),
+			_ => {
+				for pair in points.windows(2) {
+					self.draw_line(&pair[0], &pair[1], color);
+				}
+			}
+		}
+	}
 }
 
 impl Bitmap for Vec<u8> {
+	#[allow(clippy::manual_memcpy, clippy::assign_op_pattern, clippy::needless_return)]
 	fn new_bitmap(width: u32, height: u32, bpp: u16) -> Vec<u8> {
 		// Each scan line is zero padded to the nearest 4-byte boundary. If the image has a width that is not divisible by four, say, 21 bytes, there would be 3 bytes of padding at the end of every scan line.
-		let mut padded_width:u32 = width * (bpp as u32 / 8);
+		// Pixels of 1 and 4 bit images share bytes, so a partly used last byte counts as a whole one.
+		// Depths BMP does not define keep the whole byte row width they always had.
+		let indexed = matches!(bpp, 1 | 4 | 8);
+		let mut padded_width:u32 = if indexed { (width * bpp as u32).div_ceil(8) } else { width * (bpp as u32 / 8) };
 		// Compute the remainder when divided by 4
 		let remainder = padded_width % 4;
 		// If there's no remainder, the value is already a multiple of 4
 		if remainder != 0 {
 			padded_width = padded_width + (4 - remainder); // Add padding
 		}
-		let len = (padded_width * height) + TOTAL_HEADER_SIZE as u32; // Total size of bitmap, with headers and padded pixel array
+		// Indexed images of 1, 4 and 8 bits per pixel start with a grayscale color table between the headers and the pixels
+		let palette_entries: u32 = if indexed { 1 << bpp } else { 0 };
+		let pixel_array_offset = TOTAL_HEADER_SIZE + palette_entries * COLOR_TABLE_ENTRY_SIZE;
+		let len = (padded_width * height) + pixel_array_offset; // Total size of bitmap, with headers, color table and padded pixel array
 
 		let mut new_bitmap:Vec<u8> = vec![0; len as usize];
 		// Insert bitmap header signature
@@ -50,11 +262,19 @@ impl Bitmap for Vec<u8> {
 		new_bitmap[1] = b'M';
 
 		// Insert offset of pixel array in header
-		let offset_pixel_array_as_u8 = TOTAL_HEADER_SIZE.to_le_bytes();
+		let offset_pixel_array_as_u8 = pixel_array_offset.to_le_bytes();
 		for i in 0..4 {
                         new_bitmap[HEADER_PIXEL_ARRAY_OFFSET + i] = offset_pixel_array_as_u8[i]; // Convert u32 value into array of u8 (Little Endian)
                 }
 
+		// Insert the color table, evenly spaced from black to white
+		for i in 0..palette_entries {
+			let gray = (i * 255 / (palette_entries - 1)) as u8;
+			let entry = (TOTAL_HEADER_SIZE + i * COLOR_TABLE_ENTRY_SIZE) as usize;
+			new_bitmap[entry..entry + 3].copy_from_slice(&[gray, gray, gray]);
+		}
+		new_bitmap[HEADER_COLORS_USED..HEADER_COLORS_USED + 4].copy_from_slice(&palette_entries.to_le_bytes());
+
 		// Insert image width in header
 		let width_as_u8 = width.to_le_bytes(); // Convert u32 value into array of u8 (Little Endian)
 		for i in 0..4 {
@@ -80,6 +300,10 @@ impl Bitmap for Vec<u8> {
                         new_bitmap[HEADER_FILE_SIZE + i] = file_size_as_u8[i];
                 }
 
+		// Insert size of the padded pixel array in header
+		let image_size_as_u8 = (padded_width * height).to_le_bytes(); // Convert u32 value into array of u8 (Little Endian)
+		new_bitmap[HEADER_IMAGE_SIZE..HEADER_IMAGE_SIZE + 4].copy_from_slice(&image_size_as_u8);
+
 		// Insert number of planes in header
 		new_bitmap[HEADER_PLANES_OFFSET] = 1;
 
@@ -92,12 +316,33 @@ impl Bitmap for Vec<u8> {
 		return new_bitmap;
 	}
 
+	#[allow(clippy::unnecessary_cast)]
 	fn draw_point(&mut self, start: &Point, color: &Rgb) {
 		// Ensure the point is within the bounds of the bitmap
 		if !self.point_exists(start) {
 			return;
 		}
 	
+		// Indexed images store the closest color the palette has
+		if self.get_bits_per_pixel() <= 8 {
+			let index = nearest_palette_index(&self.get_palette(), color);
+			self.set_pixel_index(start, index);
+			return;
+		}
+
+		// 16 bit pixels pack the channels according to the channel masks
+		if self.get_bits_per_pixel() == 16 {
+			let index = self.get_pixel_array_offset() + start.y as usize * self.get_row_size() + start.x as usize * 2;
+			let [red, green, blue] = self.get_channel_masks();
+			let value = pack_channel(color.r, red) | pack_channel(color.g, green) | pack_channel(color.b, blue);
+			if index + 1 < self.len() {
+				self[index..index + 2].copy_from_slice(&(value as u16).to_le_bytes());
+			} else {
+				eprintln!("Index out of bounds: {}", index);
+			}
+			return;
+		}
+
 		// Convert dimensions to usize for consistent indexing
 		let width = self.get_width() as usize;
 		let bytes_per_pixel = (self.get_bits_per_pixel() as usize) / 8;
@@ -120,6 +365,7 @@ impl Bitmap for Vec<u8> {
 		}
 	}
 
+	#[allow(clippy::needless_return)]
 	fn point_exists(&self, point: &Point) -> bool {
                 if !self.has_file_signature() {
                         eprintln!("Invalid file signature. Not a Bitmap.");
@@ -128,124 +374,16 @@ impl Bitmap for Vec<u8> {
 		return (self.get_height() > point.y) && (self.get_width() > point.x);
 	}
 
-	fn draw_circle(&mut self, center: &Point, radius: u32, color: &Rgb) {
-	    // Calculate the bounding box for the circle
-	    let min_x = center.x.saturating_sub(radius);
-	    let max_x = center.x.saturating_add(radius);
-	    let min_y = center.y.saturating_sub(radius);
-	    let max_y = center.y.saturating_add(radius);
-	
-	    // Iterate over the bounding box around the circle
-	    for y in min_y..=max_y {
-	        for x in min_x..=max_x {
-	            // Calculate the distance from the center to the current point (x, y)
-	            let dx = x as i32 - center.x as i32;
-	            let dy = y as i32 - center.y as i32;
-	            if dx * dx + dy * dy <= (radius as i32) * (radius as i32) {
-	                let point = Point { x, y };
-	                self.draw_point(&point, color);
-	            }
-	        }
-	    }
-	}
-
-	fn draw_line(&mut self, start: &Point, end: &Point, color: &Rgb) {
-		let mut x0 = start.x as i32;
-		let mut y0 = start.y as i32;
-		let x1 = end.x as i32;
-		let y1 = end.y as i32;
-		let dx = (x1 - x0).abs();
-		let dy = (y1 - y0).abs();
-		let sx = if x0 < x1 { 1 } else { -1 };
-		let sy = if y0 < y1 { 1 } else { -1 };
-		let mut err = dx - dy;
-		loop {
-			self.draw_point(&Point { x: x0 as u32, y: y0 as u32 }, color);
-			if x0 == x1 && y0 == y1 {
-				break;
-			}
-			let e2 = 2 * err;
-			if e2 > -dy {
-				err -= dy;
-				x0 += sx;
-			}
-			if e2 < dx {
-				err += dx;
-				y0 += sy;
-			}
-		}
-	}
-
-	// Input two opposite corners of the rectangle
-	fn draw_rectangle(&mut self, point1: &Point, point2: &Point, color: &Rgb) {
-		let point3 = Point {x:point1.x , y:point2.y};
-		let point4 = Point {x: point2.x, y: point1.y};
-		self.draw_line(&point1, &point3, &color);
-		self.draw_line(&point3, &point2, &color);
-		self.draw_line(&point2, &point4, &color);
-		self.draw_line(&point4, &point1, &color);
-	}
-
-	fn draw_char(&mut self, char_index: usize, position: &Point, color: &Rgb) {
-		// Ensure char_index is within bounds of FONT_BITMAP
-		if char_index >= FONT_BITMAP.len() {
-			return; // or handle the error as appropriate
-		}
-
-		// Get the bitmap for the character
-		let bitmap = &FONT_BITMAP[char_index];
-
-		// Iterate over rows of the character's bitmap from bottom to top
-		for row in (0..FONT_HEIGHT).rev() {
-			// Iterate over columns of the character's bitmap from left to right
-			for col in 0..FONT_WIDTH {
-				// Calculate the index into the bitmap
-				let bit_index = (FONT_HEIGHT - 1 - row) * FONT_WIDTH + col;
-				let byte_index = bit_index / 8;
-				let bit_position = 7 - (bit_index % 8);
-	
-				// Ensure the byte index is within bounds of the bitmap array
-				if byte_index < bitmap.len() {
-					// Create a mask for the bit position
-					let mask = 1 << bit_position;
-	
-					// Check if the specific bit is set
-					if (bitmap[byte_index] & mask) != 0 {
-						// Calculate the correct x and y coordinates for the point
-						let x = position.x as u32 + (FONT_WIDTH - 1 - col) as u32;
-						let y = position.y as u32 + row as u32;
-	
-						// Draw the point
-						let point = Point { x, y };
-						self.draw_point(&point, color);
-					}
-				}
-			}
-		}
-	}
-
-
-	fn draw_string(&mut self, string: &str, position: &Point, color: &Rgb) {
-		let mut x_offset = position.x; // Start at the initial x position
-		// Iterate over each character in the string
-		for char in string.chars() {
-			// Find the index of the character in the font bitmap
-			let char_index = char as usize - 32;
-			// Draw the character at the current position
-			self.draw_char(char_index, &Point { x: x_offset, y: position.y }, color);
-			// Move the x offset by the width of the character plus any spacing
-			x_offset += FONT_WIDTH as u32;
-		}
-	}
-
-	fn draw_polygon(&mut self, points: &[Point], color: &Rgb) {
-		for i in 0..points.len()-1 {
-			self.draw_line(&points[i], &points[i+1], color);
+	// Drawing on a plain bitmap is only limited by the image bounds
+	fn clip_rect(&self) -> Rect {
+		if !self.has_file_signature() {
+			return Rect { x: 0, y: 0, width: 0, height: 0 };
 		}
-		self.draw_line(&points[0], &points[points.len()-1], color); 
+		Rect { x: 0, y: 0, width: self.get_width(), height: self.get_height() }
 	}
 
 	// Checks if a vector of bytes has the BMP file signature
+	#[allow(clippy::needless_return)]
 	fn has_file_signature(&self) -> bool {
 		return self[0] == b'B' && self[1] == b'M';
 	}
@@ -356,6 +494,7 @@ fn test_get_pixel() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_get_padding_per_line() {
         let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(100, 10, 24);
 	let padding_per_line = bmp.get_padding_per_line();
@@ -379,6 +518,7 @@ fn test_get_padding_per_line() {
 }
 
 #[test]
+#[allow(unused_mut)]
 fn test_get_padding_size() {
         let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(100, 10, 24);
         let padding = bmp.get_padding_size();
@@ -0,0 +1,221 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+
+// The neighbourhood a morphological operation looks at, centered on each pixel.
+// Stored row by row like a Kernel, the first row covering the lowest (smallest y) row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructuringElement {
+	width: usize,
+	height: usize,
+	mask: Vec<bool>,
+}
+
+impl StructuringElement {
+	// Width and height must be odd so the element has a center pixel
+	pub fn new(width: usize, height: usize, mask: &[bool]) -> Result<StructuringElement, String> {
+		if width % 2 != 1 || height % 2 != 1 {
+			return Err(format!("Structuring element size {}x{} must be odd in both directions", width, height));
+		}
+		if mask.len() != width * height {
+			return Err(format!("Structuring element of {}x{} needs {} cells, got {}", width, height, width * height, mask.len()));
+		}
+		Ok(StructuringElement { width, height, mask: mask.to_vec() })
+	}
+
+	// Builds a (2 * radius + 1) square element from a predicate on the offset from the center
+	fn from_offsets<F: Fn(i64, i64) -> bool>(radius: u32, include: F) -> StructuringElement {
+		let size = 2 * radius as usize + 1;
+		let radius = radius as i64;
+		let mask = (0..size * size).map(|index| include(index as i64 % size as i64 - radius, index as i64 / size as i64 - radius)).collect();
+		StructuringElement { width: size, height: size, mask }
+	}
+
+	// Every pixel within radius in both directions
+	pub fn square(radius: u32) -> StructuringElement {
+		StructuringElement::from_offsets(radius, |_, _| true)
+	}
+
+	// The center row and column
+	pub fn cross(radius: u32) -> StructuringElement {
+		StructuringElement::from_offsets(radius, |dx, dy| dx == 0 || dy == 0)
+	}
+
+	// Pixels whose center lies within radius of the center
+	pub fn disk(radius: u32) -> StructuringElement {
+		let limit = (radius as i64 * radius as i64) as f64 + radius as f64 * 0.5;
+		StructuringElement::from_offsets(radius, |dx, dy| ((dx * dx + dy * dy) as f64) <= limit)
+	}
+
+	// Offsets from the center of the cells that are part of the element
+	fn offsets(&self) -> Vec<(i64, i64)> {
+		let half_width = (self.width / 2) as i64;
+		let half_height = (self.height / 2) as i64;
+		(0..self.mask.len()).filter(|&index| self.mask[index]).map(|index| {
+			((index % self.width) as i64 - half_width, (index / self.width) as i64 - half_height)
+		}).collect()
+	}
+}
+
+// Takes the per channel minimum or maximum over the element around every pixel.
+// Neighbours past the edge of the image are left out, so the border neither grows nor shrinks shapes.
+fn rank_filter(bmp: &Vec<u8>, element: &StructuringElement, maximum: bool) -> Result<Vec<u8>, String> {
+	if !bmp.has_file_signature() {
+		return Err("Invalid file signature. Not a Bitmap.".to_string());
+	}
+	let width = bmp.get_width();
+	let height = bmp.get_height();
+	let mut pixels = Vec::with_capacity(width as usize * height as usize);
+	for y in 0..height {
+		for x in 0..width {
+			pixels.push(bmp.get_pixel(&Point { x, y })?);
+		}
+	}
+
+	let offsets = element.offsets();
+	// An element without cells leaves the image alone
+	if offsets.is_empty() {
+		return Ok(bmp.clone());
+	}
+	let pick = |a: u8, b: u8| if maximum { a.max(b) } else { a.min(b) };
+	let start = if maximum { 0 } else { 255 };
+	// Drawing into a copy keeps the header, palette and alpha of the source
+	let mut result = bmp.clone();
+	for y in 0..height as i64 {
+		for x in 0..width as i64 {
+			let mut color = Rgb { r: start, g: start, b: start };
+			for &(dx, dy) in &offsets {
+				let (source_x, source_y) = (x + dx, y + dy);
+				if source_x < 0 || source_y < 0 || source_x >= width as i64 || source_y >= height as i64 {
+					continue;
+				}
+				let pixel = pixels[source_y as usize * width as usize + source_x as usize];
+				color = Rgb { r: pick(color.r, pixel.r), g: pick(color.g, pixel.g), b: pick(color.b, pixel.b) };
+			}
+			result.draw_point(&Point { x: x as u32, y: y as u32 }, &color);
+		}
+	}
+	Ok(result)
+}
+
+// Per channel a - b, saturating at 0
+fn subtract(a: &Vec<u8>, b: &Vec<u8>) -> Result<Vec<u8>, String> {
+	let mut result = a.clone();
+	for y in 0..a.get_height() {
+		for x in 0..a.get_width() {
+			let point = Point { x, y };
+			let first = a.get_pixel(&point)?;
+			let second = b.get_pixel(&point)?;
+			result.draw_point(&point, &Rgb { r: first.r.saturating_sub(second.r), g: first.g.saturating_sub(second.g), b: first.b.saturating_sub(second.b) });
+		}
+	}
+	Ok(result)
+}
+
+// Bright values count as foreground: dilation grows light shapes and erosion shrinks them.
+// Color images are processed one channel at a time, and results keep the bit depth and palette of the source.
+pub trait Morphology {
+	fn erode(&self, element: &StructuringElement) -> Result<Vec<u8>, String>;
+	fn dilate(&self, element: &StructuringElement) -> Result<Vec<u8>, String>;
+	fn open(&self, element: &StructuringElement) -> Result<Vec<u8>, String>;
+	fn close(&self, element: &StructuringElement) -> Result<Vec<u8>, String>;
+	fn top_hat(&self, element: &StructuringElement) -> Result<Vec<u8>, String>;
+	fn black_hat(&self, element: &StructuringElement) -> Result<Vec<u8>, String>;
+	fn morphological_gradient(&self, element: &StructuringElement) -> Result<Vec<u8>, String>;
+}
+
+impl Morphology for Vec<u8> {
+	// Minimum over the element
+	fn erode(&self, element: &StructuringElement) -> Result<Vec<u8>, String> {
+		rank_filter(self, element, false)
+	}
+
+	// Maximum over the element
+	fn dilate(&self, element: &StructuringElement) -> Result<Vec<u8>, String> {
+		rank_filter(self, element, true)
+	}
+
+	// Erosion followed by dilation, removing bright specks smaller than the element
+	fn open(&self, element: &StructuringElement) -> Result<Vec<u8>, String> {
+		self.erode(element)?.dilate(element)
+	}
+
+	// Dilation followed by erosion, filling dark holes and gaps smaller than the element
+	fn close(&self, element: &StructuringElement) -> Result<Vec<u8>, String> {
+		self.dilate(element)?.erode(element)
+	}
+
+	// The bright details removed by opening
+	fn top_hat(&self, element: &StructuringElement) -> Result<Vec<u8>, String> {
+		subtract(self, &self.open(element)?)
+	}
+
+	// The dark details filled in by closing
+	fn black_hat(&self, element: &StructuringElement) -> Result<Vec<u8>, String> {
+		subtract(&self.close(element)?, self)
+	}
+
+	// Dilation minus erosion, outlining the edges of shapes
+	fn morphological_gradient(&self, element: &StructuringElement) -> Result<Vec<u8>, String> {
+		subtract(&self.dilate(element)?, &self.erode(element)?)
+	}
+}
+
+#[test]
+fn test_structuring_elements() {
+	let cells = |element: &StructuringElement| element.mask.iter().filter(|&&cell| cell).count();
+	assert_eq!(cells(&StructuringElement::square(1)), 9);
+	assert_eq!(cells(&StructuringElement::cross(2)), 9);
+	assert_eq!(cells(&StructuringElement::disk(1)), 5);
+	assert_eq!(cells(&StructuringElement::disk(2)), 21);
+	assert!(StructuringElement::new(2, 3, &[true; 6]).is_err());
+	assert!(StructuringElement::new(3, 1, &[true; 2]).is_err());
+
+	// A horizontal line element only grows shapes sideways
+	let line = StructuringElement::new(3, 1, &[true; 3]).unwrap();
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(5, 5, 24);
+	let white = Rgb {r: 255, g: 255, b: 255};
+	bmp.draw_point(&Point {x: 2, y: 2}, &white);
+	let dilated = bmp.dilate(&line).unwrap();
+	assert_eq!(dilated.get_pixel(&Point {x: 1, y: 2}).unwrap(), white);
+	assert_eq!(dilated.get_pixel(&Point {x: 2, y: 3}).unwrap(), Rgb {r: 0, g: 0, b: 0});
+}
+
+#[test]
+fn test_morphology_on_masks() {
+	use crate::indexed::*;
+	use crate::convert::*;
+	let black = Rgb {r: 0, g: 0, b: 0};
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let mut mask:Vec<u8> = Vec::<u8>::new_indexed_bitmap(12, 12, 1, &[black, white]).unwrap();
+	// A 5x5 square with a hole, and a stray pixel
+	for y in 2..7 {
+		for x in 2..7 {
+			mask.set_pixel_index(&Point {x, y}, 1);
+		}
+	}
+	mask.set_pixel_index(&Point {x: 4, y: 4}, 0);
+	mask.set_pixel_index(&Point {x: 10, y: 10}, 1);
+	let square = StructuringElement::square(1);
+
+	let opened = mask.open(&square).unwrap();
+	assert_eq!(opened.get_bits_per_pixel(), 1);
+	assert_eq!(opened.get_pixel_index(&Point {x: 10, y: 10}).unwrap(), 0);
+	let closed = mask.close(&square).unwrap();
+	assert_eq!(closed.get_pixel_index(&Point {x: 4, y: 4}).unwrap(), 1);
+	assert_eq!(closed.get_pixel_index(&Point {x: 10, y: 10}).unwrap(), 1);
+
+	let eroded = mask.erode(&square).unwrap();
+	assert_eq!(eroded.get_pixel_index(&Point {x: 3, y: 3}).unwrap(), 0);
+	assert_eq!(mask.top_hat(&square).unwrap().get_pixel_index(&Point {x: 10, y: 10}).unwrap(), 1);
+	assert_eq!(mask.black_hat(&square).unwrap().get_pixel_index(&Point {x: 4, y: 4}).unwrap(), 1);
+
+	// The gradient of a 24 bit gray image outlines the square on both sides of its edge
+	let gray = mask.convert(24, &ConvertOptions::default()).unwrap();
+	let gradient = gray.morphological_gradient(&square).unwrap();
+	assert_eq!(gradient.get_bits_per_pixel(), 24);
+	assert_eq!(gradient.get_pixel(&Point {x: 1, y: 2}).unwrap(), white);
+	assert_eq!(gradient.get_pixel(&Point {x: 2, y: 2}).unwrap(), white);
+	assert_eq!(gradient.get_pixel(&Point {x: 0, y: 0}).unwrap(), black);
+}
@@ -0,0 +1,260 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::path::*;
+use crate::signed::*;
+
+// What happens to a gradient outside the 0..1 range between its first and last stop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpreadMode {
+	// Extend the end colors
+	Pad,
+	// Start over from the first stop
+	Repeat,
+	// Run back and forth between the stops
+	Reflect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorStop {
+	pub offset: f64,
+	pub color: Rgb
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+	stops: Vec<ColorStop>,
+	spread: SpreadMode,
+}
+
+impl Gradient {
+	// Stops may be given in any order; offsets are expected in 0..1
+	pub fn new(stops: &[ColorStop], spread: SpreadMode) -> Gradient {
+		let mut stops = stops.to_vec();
+		stops.sort_by(|a, b| a.offset.partial_cmp(&b.offset).unwrap_or(std::cmp::Ordering::Equal));
+		Gradient { stops, spread }
+	}
+
+	// Color at position t along the gradient
+	pub fn color_at(&self, t: f64) -> Rgb {
+		if self.stops.is_empty() {
+			return Rgb { r: 0, g: 0, b: 0 };
+		}
+		let t = match self.spread {
+			SpreadMode::Pad => t,
+			SpreadMode::Repeat => t - t.floor(),
+			SpreadMode::Reflect => {
+				let t = t.abs() % 2.0;
+				if t > 1.0 { 2.0 - t } else { t }
+			}
+		};
+
+		let first = &self.stops[0];
+		let last = &self.stops[self.stops.len() - 1];
+		if t <= first.offset {
+			return first.color;
+		}
+		if t >= last.offset {
+			return last.color;
+		}
+		for pair in self.stops.windows(2) {
+			if t <= pair[1].offset {
+				let span = pair[1].offset - pair[0].offset;
+				let amount = if span > 0.0 { (t - pair[0].offset) / span } else { 1.0 };
+				return lerp_rgb(&pair[0].color, &pair[1].color, amount);
+			}
+		}
+		last.color
+	}
+}
+
+fn lerp_rgb(a: &Rgb, b: &Rgb, amount: f64) -> Rgb {
+	let channel = |a: u8, b: u8| -> u8 { (a as f64 + (b as f64 - a as f64) * amount).round() as u8 };
+	Rgb { r: channel(a.r, b.r), g: channel(a.g, b.g), b: channel(a.b, b.b) }
+}
+
+// The source of color for fill operations
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+	Solid(Rgb),
+	// Varies along the line from start to end
+	Linear { start: FloatPoint, end: FloatPoint, gradient: Gradient },
+	// Varies with the distance from center, reaching offset 1.0 at radius
+	Radial { center: FloatPoint, radius: f64, gradient: Gradient },
+	// Varies with the angle around center, beginning at angle degrees and sweeping counterclockwise
+	Conic { center: FloatPoint, angle: f64, gradient: Gradient },
+	// Tiles another bitmap across the plane. The tile's pixel (0, 0) lands on offset and each tile pixel covers scale x scale target pixels.
+	Pattern { image: Vec<u8>, offset: SignedPoint, scale: f64 },
+}
+
+impl Paint {
+	// Color of the pixel at (x, y), sampled at the pixel's center
+	pub fn color_at(&self, x: u32, y: u32) -> Rgb {
+		let px = x as f64 + 0.5;
+		let py = y as f64 + 0.5;
+		match self {
+			Paint::Solid(color) => *color,
+			Paint::Linear { start, end, gradient } => {
+				let dx = end.x - start.x;
+				let dy = end.y - start.y;
+				let length_squared = dx * dx + dy * dy;
+				let t = if length_squared > 0.0 { ((px - start.x) * dx + (py - start.y) * dy) / length_squared } else { 0.0 };
+				gradient.color_at(t)
+			}
+			Paint::Radial { center, radius, gradient } => {
+				let distance = ((px - center.x).powi(2) + (py - center.y).powi(2)).sqrt();
+				let t = if *radius > 0.0 { distance / radius } else { 1.0 };
+				gradient.color_at(t)
+			}
+			Paint::Conic { center, angle, gradient } => {
+				let theta = (py - center.y).atan2(px - center.x) - angle.to_radians();
+				let turns = theta / (2.0 * std::f64::consts::PI);
+				gradient.color_at(turns - turns.floor())
+			}
+			Paint::Pattern { image, offset, scale } => {
+				let width = image.get_width() as i64;
+				let height = image.get_height() as i64;
+				if width == 0 || height == 0 || *scale <= 0.0 {
+					return Rgb { r: 0, g: 0, b: 0 };
+				}
+				// Wrap in both directions so the pattern also repeats left of and below the offset
+				let source_x = ((x as f64 - offset.x as f64) / scale).floor() as i64;
+				let source_y = ((y as f64 - offset.y as f64) / scale).floor() as i64;
+				let point = Point { x: source_x.rem_euclid(width) as u32, y: source_y.rem_euclid(height) as u32 };
+				image.get_pixel(&point).unwrap_or(Rgb { r: 0, g: 0, b: 0 })
+			}
+		}
+	}
+}
+
+pub trait PaintFill {
+	fn fill_rectangle(&mut self, point1: &Point, point2: &Point, paint: &Paint);
+	fn fill_circle(&mut self, center: &Point, radius: u32, paint: &Paint);
+	fn fill_polygon(&mut self, points: &[Point], paint: &Paint);
+	fn fill_path_with_paint(&mut self, path: &Path, rule: FillRule, paint: &Paint);
+}
+
+impl<T: Bitmap> PaintFill for T {
+	// Input two opposite corners of the rectangle; both are included
+	fn fill_rectangle(&mut self, point1: &Point, point2: &Point, paint: &Paint) {
+		let clip = self.clip_rect();
+		if clip.is_empty() {
+			return;
+		}
+		let min_x = point1.x.min(point2.x).max(clip.x);
+		let max_x = point1.x.max(point2.x).min(clip.x + clip.width - 1);
+		let min_y = point1.y.min(point2.y).max(clip.y);
+		let max_y = point1.y.max(point2.y).min(clip.y + clip.height - 1);
+		for y in min_y..=max_y {
+			for x in min_x..=max_x {
+				self.draw_point(&Point { x, y }, &paint.color_at(x, y));
+			}
+		}
+	}
+
+	// Covers the same pixels as draw_circle
+	fn fill_circle(&mut self, center: &Point, radius: u32, paint: &Paint) {
+		let clip = self.clip_rect();
+		disk_spans(center.x as f64, center.y as f64, radius as f64, &clip, |y, x_start, x_end| {
+			for x in x_start..=x_end {
+				self.draw_point(&Point { x, y }, &paint.color_at(x, y));
+			}
+		});
+	}
+
+	// Fills the pixels whose centers are inside the polygon, using the non-zero rule.
+	// Each vertex is placed at the center of its pixel, where draw_polygon's outline passes through it.
+	fn fill_polygon(&mut self, points: &[Point], paint: &Paint) {
+		let polygon: Vec<(f64, f64)> = points.iter().map(|point| (point.x as f64 + 0.5, point.y as f64 + 0.5)).collect();
+		let clip = self.clip_rect();
+		rasterize_polygons(&[polygon], FillRule::NonZero, &clip, |y, x_start, x_end| {
+			for x in x_start..=x_end {
+				self.draw_point(&Point { x, y }, &paint.color_at(x, y));
+			}
+		});
+	}
+
+	fn fill_path_with_paint(&mut self, path: &Path, rule: FillRule, paint: &Paint) {
+		let polygons: Vec<Vec<(f64, f64)>> = path.flatten(crate::curve::CURVE_TOLERANCE).into_iter().map(|subpath| subpath.points).collect();
+		let clip = self.clip_rect();
+		rasterize_polygons(&polygons, rule, &clip, |y, x_start, x_end| {
+			for x in x_start..=x_end {
+				self.draw_point(&Point { x, y }, &paint.color_at(x, y));
+			}
+		});
+	}
+}
+
+#[test]
+fn test_gradient_spread_modes() {
+	let black = Rgb {r: 0, g: 0, b: 0};
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let stops = [ColorStop {offset: 1.0, color: white}, ColorStop {offset: 0.0, color: black}];
+
+	let pad = Gradient::new(&stops, SpreadMode::Pad);
+	assert_eq!(pad.color_at(-1.0), black);
+	assert_eq!(pad.color_at(0.5), Rgb {r: 128, g: 128, b: 128});
+	assert_eq!(pad.color_at(1.5), white);
+
+	let repeat = Gradient::new(&stops, SpreadMode::Repeat);
+	assert_eq!(repeat.color_at(1.25), Rgb {r: 64, g: 64, b: 64});
+
+	let reflect = Gradient::new(&stops, SpreadMode::Reflect);
+	assert_eq!(reflect.color_at(1.25), Rgb {r: 191, g: 191, b: 191});
+}
+
+#[test]
+fn test_fill_with_gradients() {
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(88, 31, 24);
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let blue = Rgb {r: 0, g: 0, b: 255};
+	let gradient = Gradient::new(&[ColorStop {offset: 0.0, color: red}, ColorStop {offset: 1.0, color: blue}], SpreadMode::Pad);
+
+	let linear = Paint::Linear { start: FloatPoint {x: 0.0, y: 0.0}, end: FloatPoint {x: 88.0, y: 0.0}, gradient: gradient.clone() };
+	bmp.fill_rectangle(&Point {x: 0, y: 0}, &Point {x: 87, y: 30}, &linear);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 15}).unwrap(), Rgb {r: 254, g: 0, b: 1});
+	assert_eq!(bmp.get_pixel(&Point {x: 87, y: 15}).unwrap(), Rgb {r: 1, g: 0, b: 254});
+
+	let radial = Paint::Radial { center: FloatPoint {x: 44.5, y: 15.5}, radius: 10.0, gradient };
+	bmp.fill_circle(&Point {x: 44, y: 15}, 10, &radial);
+	assert_eq!(bmp.get_pixel(&Point {x: 44, y: 15}).unwrap(), red);
+
+	let conic = Paint::Conic { center: FloatPoint {x: 10.0, y: 10.0}, angle: 0.0, gradient: Gradient::new(&[ColorStop {offset: 0.0, color: red}, ColorStop {offset: 0.5, color: blue}], SpreadMode::Pad) };
+	bmp.fill_polygon(&[Point {x: 0, y: 0}, Point {x: 20, y: 0}, Point {x: 20, y: 20}, Point {x: 0, y: 20}], &conic);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 9}).unwrap(), blue);
+}
+
+#[test]
+fn test_fill_with_pattern() {
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let black = Rgb {r: 0, g: 0, b: 0};
+	// A 2x2 checkerboard tile
+	let mut tile:Vec<u8> = Vec::<u8>::new_bitmap(2, 2, 24);
+	tile.draw_point(&Point {x: 0, y: 0}, &white);
+	tile.draw_point(&Point {x: 1, y: 1}, &white);
+
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(12, 12, 24);
+	let pattern = Paint::Pattern { image: tile, offset: SignedPoint {x: 1, y: 0}, scale: 2.0 };
+	bmp.fill_rectangle(&Point {x: 0, y: 0}, &Point {x: 11, y: 11}, &pattern);
+	assert_eq!(bmp.get_pixel(&Point {x: 1, y: 0}).unwrap(), white);
+	assert_eq!(bmp.get_pixel(&Point {x: 2, y: 1}).unwrap(), white);
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 0}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 0}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 2}).unwrap(), white);
+}
+
+#[test]
+fn test_fill_polygon_matches_fill_path() {
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let points = [Point {x: 2, y: 1}, Point {x: 17, y: 4}, Point {x: 9, y: 15}];
+	let mut polygon:Vec<u8> = Vec::<u8>::new_bitmap(20, 20, 24);
+	polygon.fill_polygon(&points, &Paint::Solid(red));
+
+	// The same triangle as a path through the pixel centers of the vertices
+	let path = Path::new().move_to(2.5, 1.5).line_to(17.5, 4.5).line_to(9.5, 15.5).close();
+	let mut filled:Vec<u8> = Vec::<u8>::new_bitmap(20, 20, 24);
+	filled.fill_path(&path, FillRule::NonZero, &red);
+	assert_eq!(polygon, filled);
+	assert_eq!(polygon.get_pixel(&Point {x: 9, y: 6}).unwrap(), red);
+}
@@ -0,0 +1,331 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Rect;
+use crate::curve::*;
+use crate::signed::*;
+
+// Decides which regions of a self-intersecting or nested path count as inside
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FillRule {
+	NonZero,
+	EvenOdd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathCommand {
+	MoveTo(f64, f64),
+	LineTo(f64, f64),
+	QuadTo(f64, f64, f64, f64),
+	CubicTo(f64, f64, f64, f64, f64, f64),
+	ArcTo { rx: f64, ry: f64, rotation: f64, large_arc: bool, sweep: bool, x: f64, y: f64 },
+	Close,
+}
+
+// A sequence of drawing commands, modelled after SVG path data.
+// Coordinates are in pixels and may be fractional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+	commands: Vec<PathCommand>,
+}
+
+// A flattened subpath: its vertices and whether it was explicitly closed
+pub struct Subpath {
+	pub points: Vec<(f64, f64)>,
+	pub closed: bool,
+}
+
+impl Path {
+	pub fn new() -> Path {
+		Path { commands: Vec::new() }
+	}
+
+	// Starts a new subpath at (x, y)
+	pub fn move_to(mut self, x: f64, y: f64) -> Path {
+		self.commands.push(PathCommand::MoveTo(x, y));
+		self
+	}
+
+	pub fn line_to(mut self, x: f64, y: f64) -> Path {
+		self.commands.push(PathCommand::LineTo(x, y));
+		self
+	}
+
+	// Quadratic Bézier with control point (cx, cy) ending at (x, y)
+	pub fn quad_to(mut self, cx: f64, cy: f64, x: f64, y: f64) -> Path {
+		self.commands.push(PathCommand::QuadTo(cx, cy, x, y));
+		self
+	}
+
+	// Cubic Bézier with control points (c1x, c1y) and (c2x, c2y) ending at (x, y)
+	pub fn cubic_to(mut self, c1x: f64, c1y: f64, c2x: f64, c2y: f64, x: f64, y: f64) -> Path {
+		self.commands.push(PathCommand::CubicTo(c1x, c1y, c2x, c2y, x, y));
+		self
+	}
+
+	// Elliptical arc ending at (x, y), with the same parameters as the SVG "A" command.
+	// rotation is the rotation of the ellipse's x axis in degrees.
+	#[allow(clippy::too_many_arguments)]
+	pub fn arc_to(mut self, rx: f64, ry: f64, rotation: f64, large_arc: bool, sweep: bool, x: f64, y: f64) -> Path {
+		self.commands.push(PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y });
+		self
+	}
+
+	// Closes the current subpath with a straight line back to its start
+	pub fn close(mut self) -> Path {
+		self.commands.push(PathCommand::Close);
+		self
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.commands.is_empty()
+	}
+
+	// Converts every subpath into a list of line segment vertices
+	pub fn flatten(&self, tolerance: f64) -> Vec<Subpath> {
+		let mut subpaths: Vec<Subpath> = Vec::new();
+		let mut current: Vec<(f64, f64)> = Vec::new();
+		let mut start = (0.0, 0.0);
+		let mut pen = (0.0, 0.0);
+
+		for command in self.commands.iter() {
+			// Commands other than move_to continue from the pen position, starting a subpath if needed
+			if current.is_empty() && !matches!(command, PathCommand::MoveTo(..) | PathCommand::Close) {
+				start = pen;
+				current.push(pen);
+			}
+			match *command {
+				PathCommand::MoveTo(x, y) => {
+					if !current.is_empty() {
+						subpaths.push(Subpath { points: std::mem::take(&mut current), closed: false });
+					}
+					start = (x, y);
+					pen = (x, y);
+					current.push(pen);
+				}
+				PathCommand::LineTo(x, y) => {
+					pen = (x, y);
+					current.push(pen);
+				}
+				PathCommand::QuadTo(cx, cy, x, y) => {
+					let flattened = flatten_quadratic(pen, (cx, cy), (x, y), tolerance);
+					current.extend_from_slice(&flattened[1..]);
+					pen = (x, y);
+				}
+				PathCommand::CubicTo(c1x, c1y, c2x, c2y, x, y) => {
+					let flattened = flatten_cubic(pen, (c1x, c1y), (c2x, c2y), (x, y), tolerance);
+					current.extend_from_slice(&flattened[1..]);
+					pen = (x, y);
+				}
+				PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y } => {
+					flatten_arc(pen, rx, ry, rotation, large_arc, sweep, (x, y), tolerance, &mut current);
+					pen = (x, y);
+				}
+				PathCommand::Close => {
+					if !current.is_empty() {
+						subpaths.push(Subpath { points: std::mem::take(&mut current), closed: true });
+					}
+					pen = start;
+				}
+			}
+		}
+		if !current.is_empty() {
+			subpaths.push(Subpath { points: current, closed: false });
+		}
+		subpaths
+	}
+}
+
+// Appends the points of an SVG-style elliptical arc from `from` to `to`, excluding `from`.
+// Follows the endpoint to center parameterization of the SVG specification, appendix F.6.
+#[allow(clippy::too_many_arguments)]
+fn flatten_arc(from: (f64, f64), rx: f64, ry: f64, rotation: f64, large_arc: bool, sweep: bool, to: (f64, f64), tolerance: f64, output: &mut Vec<(f64, f64)>) {
+	let mut rx = rx.abs();
+	let mut ry = ry.abs();
+	if from == to {
+		return;
+	}
+	// Out of range radii degrade to a straight line
+	if rx == 0.0 || ry == 0.0 {
+		output.push(to);
+		return;
+	}
+
+	let (sin_phi, cos_phi) = rotation.to_radians().sin_cos();
+	let half_dx = (from.0 - to.0) / 2.0;
+	let half_dy = (from.1 - to.1) / 2.0;
+	let x1p = cos_phi * half_dx + sin_phi * half_dy;
+	let y1p = -sin_phi * half_dx + cos_phi * half_dy;
+
+	// Scale up radii that are too small to span both endpoints
+	let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+	if lambda > 1.0 {
+		rx *= lambda.sqrt();
+		ry *= lambda.sqrt();
+	}
+
+	let numerator = rx * rx * ry * ry - rx * rx * y1p * y1p - ry * ry * x1p * x1p;
+	let denominator = rx * rx * y1p * y1p + ry * ry * x1p * x1p;
+	let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+	if large_arc == sweep {
+		coefficient = -coefficient;
+	}
+	let cxp = coefficient * rx * y1p / ry;
+	let cyp = -coefficient * ry * x1p / rx;
+	let cx = cos_phi * cxp - sin_phi * cyp + (from.0 + to.0) / 2.0;
+	let cy = sin_phi * cxp + cos_phi * cyp + (from.1 + to.1) / 2.0;
+
+	let start_angle = ((y1p - cyp) / ry).atan2((x1p - cxp) / rx);
+	let end_angle = ((-y1p - cyp) / ry).atan2((-x1p - cxp) / rx);
+	let mut sweep_angle = end_angle - start_angle;
+	if sweep && sweep_angle < 0.0 {
+		sweep_angle += 2.0 * std::f64::consts::PI;
+	} else if !sweep && sweep_angle > 0.0 {
+		sweep_angle -= 2.0 * std::f64::consts::PI;
+	}
+
+	// Pick the angular step so the chord never strays further than the tolerance from the arc
+	let radius = rx.max(ry);
+	let step = if radius > tolerance { 2.0 * (1.0 - tolerance / radius).acos() } else { std::f64::consts::FRAC_PI_2 };
+	let segments = (sweep_angle.abs() / step).ceil().max(1.0) as usize;
+	for i in 1..segments {
+		let angle = start_angle + sweep_angle * (i as f64 / segments as f64);
+		let (sin, cos) = angle.sin_cos();
+		let x = cx + rx * cos * cos_phi - ry * sin * sin_phi;
+		let y = cy + rx * cos * sin_phi + ry * sin * cos_phi;
+		output.push((x, y));
+	}
+	output.push(to);
+}
+
+// Scan converts closed polygons, calling span(y, x_start, x_end) for each horizontal run of inside pixels.
+// A pixel is inside when its center is inside according to the fill rule. Spans are clipped to clip and x_end is inclusive.
+pub fn rasterize_polygons<F: FnMut(u32, u32, u32)>(polygons: &[Vec<(f64, f64)>], rule: FillRule, clip: &Rect, mut span: F) {
+	// Collect every non-horizontal edge as (x0, y0, x1, y1, winding direction)
+	let mut edges: Vec<(f64, f64, f64, f64, i32)> = Vec::new();
+	let mut min_y = f64::MAX;
+	let mut max_y = f64::MIN;
+	for polygon in polygons.iter() {
+		for i in 0..polygon.len() {
+			let a = polygon[i];
+			let b = polygon[(i + 1) % polygon.len()];
+			if a.1 == b.1 {
+				continue;
+			}
+			min_y = min_y.min(a.1.min(b.1));
+			max_y = max_y.max(a.1.max(b.1));
+			if a.1 < b.1 {
+				edges.push((a.0, a.1, b.0, b.1, 1));
+			} else {
+				edges.push((b.0, b.1, a.0, a.1, -1));
+			}
+		}
+	}
+	if edges.is_empty() || clip.is_empty() {
+		return;
+	}
+
+	let clip_left = clip.x as f64;
+	let clip_right = clip.x as f64 + clip.width as f64 - 1.0;
+	let first_row = (min_y - 0.5).ceil().max(clip.y as f64);
+	let last_row = (max_y - 0.5).floor().min(clip.y as f64 + clip.height as f64 - 1.0);
+	if last_row < first_row {
+		return;
+	}
+	let (first_row, last_row) = (first_row as u32, last_row as u32);
+
+	let mut crossings: Vec<(f64, i32)> = Vec::new();
+	for y in first_row..=last_row {
+		let sample_y = y as f64 + 0.5;
+		crossings.clear();
+		for &(x0, y0, x1, y1, direction) in edges.iter() {
+			// Half-open interval so shared vertices are only counted once
+			if sample_y >= y0 && sample_y < y1 {
+				let x = x0 + (sample_y - y0) * (x1 - x0) / (y1 - y0);
+				crossings.push((x, direction));
+			}
+		}
+		crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+		let mut winding = 0;
+		for i in 0..crossings.len().saturating_sub(1) {
+			winding += crossings[i].1;
+			let inside = match rule {
+				FillRule::NonZero => winding != 0,
+				FillRule::EvenOdd => winding % 2 != 0,
+			};
+			if !inside {
+				continue;
+			}
+			// Pixels whose centers fall between the two crossings
+			let start = (crossings[i].0 - 0.5).ceil().max(clip_left);
+			let end = ((crossings[i + 1].0 - 0.5).ceil() - 1.0).min(clip_right);
+			if end >= start {
+				span(y, start as u32, end as u32);
+			}
+		}
+	}
+}
+
+pub trait DrawPath {
+	fn stroke_path(&mut self, path: &Path, color: &Rgb);
+	fn fill_path(&mut self, path: &Path, rule: FillRule, color: &Rgb);
+}
+
+impl<T: Bitmap> DrawPath for T {
+	// Outlines every subpath using the existing line primitives
+	fn stroke_path(&mut self, path: &Path, color: &Rgb) {
+		for subpath in path.flatten(CURVE_TOLERANCE) {
+			let mut points = to_points(&subpath.points);
+			if subpath.closed && points.len() > 1 {
+				points.push(points[0]);
+			}
+			self.draw_signed_polyline(&points, color);
+		}
+	}
+
+	// Fills the interior of the path; open subpaths are implicitly closed
+	fn fill_path(&mut self, path: &Path, rule: FillRule, color: &Rgb) {
+		let polygons: Vec<Vec<(f64, f64)>> = path.flatten(CURVE_TOLERANCE).into_iter().map(|subpath| subpath.points).collect();
+		let clip = self.clip_rect();
+		rasterize_polygons(&polygons, rule, &clip, |y, x_start, x_end| {
+			for x in x_start..=x_end {
+				self.draw_point(&Point { x, y }, color);
+			}
+		});
+	}
+}
+
+#[test]
+fn test_fill_path_rules() {
+	use crate::Get;
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(30, 30, 24);
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let black = Rgb {r: 0, g: 0, b: 0};
+
+	// Two nested squares wound in the same direction: the hole only appears with even-odd
+	let path = Path::new()
+		.move_to(2.0, 2.0).line_to(28.0, 2.0).line_to(28.0, 28.0).line_to(2.0, 28.0).close()
+		.move_to(10.0, 10.0).line_to(20.0, 10.0).line_to(20.0, 20.0).line_to(10.0, 20.0).close();
+	bmp.fill_path(&path, FillRule::EvenOdd, &red);
+	assert_eq!(bmp.get_pixel(&Point {x: 5, y: 5}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 15, y: 15}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 29, y: 29}).unwrap(), black);
+
+	bmp.fill_path(&path, FillRule::NonZero, &red);
+	assert_eq!(bmp.get_pixel(&Point {x: 15, y: 15}).unwrap(), red);
+}
+
+#[test]
+fn test_arc_to_reaches_endpoint() {
+	// A half circle of radius 10 from (0, 10) to (20, 10)
+	let path = Path::new().move_to(0.0, 10.0).arc_to(10.0, 10.0, 0.0, false, true, 20.0, 10.0);
+	let subpaths = path.flatten(CURVE_TOLERANCE);
+	assert_eq!(subpaths.len(), 1);
+	let points = &subpaths[0].points;
+	assert_eq!(*points.last().unwrap(), (20.0, 10.0));
+	for &(x, y) in points.iter() {
+		let distance = ((x - 10.0).powi(2) + (y - 10.0).powi(2)).sqrt();
+		assert!((distance - 10.0).abs() < 1e-9);
+	}
+}
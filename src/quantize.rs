@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::resample::read_pixels;
+use crate::stats::for_each_pixel;
+use crate::indexed::*;
+
+// How the palette is chosen from the colors of the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantizeMethod {
+	// Repeatedly splits the box of colors with the widest channel range at its median
+	MedianCut,
+	// Merges the least used branches of an octree of color bits
+	Octree,
+	// Refines a median cut palette by moving each entry to the mean of the colors closest to it
+	KMeans { iterations: u32 },
+}
+
+// How colors between palette entries are approximated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dither {
+	// Every pixel takes its nearest palette entry
+	None,
+	// Error diffusion over four neighbours, good general purpose choice
+	FloydSteinberg,
+	// Error diffusion that drops a quarter of the error, keeping more contrast
+	Atkinson,
+	// Ordered dithering with a 2x2, 4x4 or 8x8 threshold matrix, stable when the image is animated or edited
+	Bayer2,
+	Bayer4,
+	Bayer8,
+}
+
+fn channel(color: &Rgb, index: usize) -> u8 {
+	match index {
+		0 => color.r,
+		1 => color.g,
+		_ => color.b,
+	}
+}
+
+// Count weighted mean of a group of colors
+fn average(colors: &[(Rgb, u32)]) -> Rgb {
+	let mut sums = [0u64; 3];
+	let mut total = 0u64;
+	for (color, count) in colors {
+		for (i, sum) in sums.iter_mut().enumerate() {
+			*sum += channel(color, i) as u64 * *count as u64;
+		}
+		total += *count as u64;
+	}
+	let mean = |sum: u64| ((sum + total / 2) / total.max(1)) as u8;
+	Rgb { r: mean(sums[0]), g: mean(sums[1]), b: mean(sums[2]) }
+}
+
+// The channel with the widest spread and that spread
+fn widest_channel(colors: &[(Rgb, u32)]) -> (usize, u8) {
+	(0..3).map(|i| {
+		let min = colors.iter().map(|(color, _)| channel(color, i)).min().unwrap_or(0);
+		let max = colors.iter().map(|(color, _)| channel(color, i)).max().unwrap_or(0);
+		(i, max - min)
+	}).max_by_key(|&(i, range)| (range, std::cmp::Reverse(i))).unwrap_or((0, 0))
+}
+
+fn median_cut(colors: &[(Rgb, u32)], count: usize) -> Vec<Rgb> {
+	let mut boxes = vec![colors.to_vec()];
+	while boxes.len() < count {
+		// Split the box with the widest range; boxes holding one color cannot be split
+		let Some((index, (split_channel, _))) = boxes.iter().enumerate()
+			.filter(|(_, colors)| colors.len() > 1)
+			.map(|(index, colors)| (index, widest_channel(colors)))
+			.max_by_key(|&(index, (_, range))| (range, std::cmp::Reverse(index))) else {
+			break;
+		};
+		let mut colors = boxes.swap_remove(index);
+		colors.sort_by_key(|(color, _)| channel(color, split_channel));
+		let total: u64 = colors.iter().map(|(_, count)| *count as u64).sum();
+		let mut seen = 0u64;
+		let mut split = colors.len() - 1;
+		for (i, (_, count)) in colors.iter().enumerate() {
+			seen += *count as u64;
+			if seen * 2 >= total {
+				split = i + 1;
+				break;
+			}
+		}
+		let upper = colors.split_off(split.clamp(1, colors.len() - 1));
+		boxes.push(colors);
+		boxes.push(upper);
+	}
+	boxes.iter().map(|colors| average(colors)).collect()
+}
+
+struct OctreeNode {
+	children: [Option<usize>; 8],
+	sums: [u64; 3],
+	pixels: u64,
+	leaf: bool,
+}
+
+fn octree(colors: &[(Rgb, u32)], count: usize) -> Vec<Rgb> {
+	let mut nodes = vec![OctreeNode { children: [None; 8], sums: [0; 3], pixels: 0, leaf: false }];
+	// Nodes that have children, by depth
+	let mut levels: Vec<Vec<usize>> = vec![Vec::new(); 8];
+	levels[0].push(0);
+	let mut leaves = 0;
+
+	// Every node accumulates the colors below it, so reducing a node only means dropping its children
+	for (color, pixels) in colors {
+		let mut node = 0;
+		for depth in 0..8 {
+			let shift = 7 - depth;
+			let child = ((((color.r >> shift) & 1) << 2) | (((color.g >> shift) & 1) << 1) | ((color.b >> shift) & 1)) as usize;
+			for (i, sum) in nodes[node].sums.iter_mut().enumerate() {
+				*sum += channel(color, i) as u64 * *pixels as u64;
+			}
+			nodes[node].pixels += *pixels as u64;
+			node = match nodes[node].children[child] {
+				Some(next) => next,
+				None => {
+					nodes.push(OctreeNode { children: [None; 8], sums: [0; 3], pixels: 0, leaf: depth == 7 });
+					let next = nodes.len() - 1;
+					nodes[node].children[child] = Some(next);
+					if depth < 7 {
+						levels[depth + 1].push(next);
+					} else {
+						leaves += 1;
+					}
+					next
+				}
+			};
+		}
+		for (i, sum) in nodes[node].sums.iter_mut().enumerate() {
+			*sum += channel(color, i) as u64 * *pixels as u64;
+		}
+		nodes[node].pixels += *pixels as u64;
+	}
+
+	// Fold the deepest, least used branches first until the palette fits
+	for depth in (0..8).rev() {
+		let mut candidates = levels[depth].clone();
+		candidates.sort_by_key(|&node| (nodes[node].pixels, node));
+		for node in candidates {
+			if leaves <= count {
+				break;
+			}
+			let mut children: Vec<(usize, usize)> = nodes[node].children.iter().enumerate().filter_map(|(slot, child)| child.map(|child| (slot, child))).collect();
+			if children.len() - 1 <= leaves - count {
+				nodes[node].children = [None; 8];
+				nodes[node].leaf = true;
+				leaves = leaves + 1 - children.len();
+			} else {
+				// Folding every child would leave too few colors, so only the least used children are combined into one
+				children.sort_by_key(|&(_, child)| (nodes[child].pixels, child));
+				let (_, target) = children[0];
+				for &(slot, child) in &children[1..=leaves - count] {
+					let (sums, pixels) = (nodes[child].sums, nodes[child].pixels);
+					for (sum, extra) in nodes[target].sums.iter_mut().zip(sums) {
+						*sum += extra;
+					}
+					nodes[target].pixels += pixels;
+					nodes[node].children[slot] = None;
+				}
+				leaves = count;
+			}
+		}
+	}
+
+	let mut palette = Vec::with_capacity(leaves);
+	let mut stack = vec![0];
+	while let Some(node) = stack.pop() {
+		if nodes[node].leaf {
+			let pixels = nodes[node].pixels.max(1);
+			let mean = |sum: u64| ((sum + pixels / 2) / pixels) as u8;
+			palette.push(Rgb { r: mean(nodes[node].sums[0]), g: mean(nodes[node].sums[1]), b: mean(nodes[node].sums[2]) });
+		} else {
+			stack.extend(nodes[node].children.iter().rev().flatten());
+		}
+	}
+	palette
+}
+
+fn k_means(colors: &[(Rgb, u32)], count: usize, iterations: u32) -> Vec<Rgb> {
+	let mut palette = median_cut(colors, count);
+	let mut assignment = vec![usize::MAX; colors.len()];
+	for _ in 0..iterations {
+		let mut changed = false;
+		for (i, (color, _)) in colors.iter().enumerate() {
+			let nearest = nearest_palette_index(&palette, color) as usize;
+			if assignment[i] != nearest {
+				assignment[i] = nearest;
+				changed = true;
+			}
+		}
+		if !changed {
+			break;
+		}
+		let mut members: Vec<Vec<(Rgb, u32)>> = vec![Vec::new(); palette.len()];
+		for (color, &cluster) in colors.iter().zip(assignment.iter()) {
+			members[cluster].push(*color);
+		}
+		// Entries that lost all their colors keep their place
+		for (center, members) in palette.iter_mut().zip(members.iter()) {
+			if !members.is_empty() {
+				*center = average(members);
+			}
+		}
+	}
+	palette
+}
+
+// Picks at most count colors representing the given colors, each weighted by its pixel count
+pub fn build_palette(colors: &[(Rgb, u32)], count: usize, method: QuantizeMethod) -> Vec<Rgb> {
+	if colors.is_empty() || count == 0 {
+		return Vec::new();
+	}
+	if colors.len() <= count {
+		return colors.iter().map(|(color, _)| *color).collect();
+	}
+	match method {
+		QuantizeMethod::MedianCut => median_cut(colors, count),
+		QuantizeMethod::Octree => octree(colors, count),
+		QuantizeMethod::KMeans { iterations } => k_means(colors, count, iterations),
+	}
+}
+
+// Builds the 2^n x 2^n Bayer matrix by repeatedly tiling the previous one
+fn bayer_matrix(size: usize) -> Vec<Vec<f64>> {
+	let mut matrix = vec![vec![0.0]];
+	while matrix.len() < size {
+		let n = matrix.len();
+		let mut next = vec![vec![0.0; 2 * n]; 2 * n];
+		for y in 0..n {
+			for x in 0..n {
+				let value = 4.0 * matrix[y][x];
+				next[y][x] = value;
+				next[y][x + n] = value + 2.0;
+				next[y + n][x] = value + 3.0;
+				next[y + n][x + n] = value + 1.0;
+			}
+		}
+		matrix = next;
+	}
+	matrix
+}
+
+pub trait Quantize {
+	fn generate_palette(&self, count: usize, method: QuantizeMethod) -> Result<Vec<Rgb>, String>;
+	fn remap_to_palette(&self, palette: &[Rgb], bpp: u16, dither: Dither) -> Result<Vec<u8>, String>;
+	fn quantize(&self, bpp: u16, method: QuantizeMethod, dither: Dither) -> Result<Vec<u8>, String>;
+}
+
+impl Quantize for Vec<u8> {
+	fn generate_palette(&self, count: usize, method: QuantizeMethod) -> Result<Vec<Rgb>, String> {
+		let mut counts: HashMap<Rgb, u32> = HashMap::new();
+		for_each_pixel(self, |color| *counts.entry(color).or_insert(0) += 1)?;
+		// Sorted so the palette does not depend on hash order
+		let mut colors: Vec<(Rgb, u32)> = counts.into_iter().collect();
+		colors.sort_by_key(|(color, _)| (color.r, color.g, color.b));
+		Ok(build_palette(&colors, count, method))
+	}
+
+	// Writes a new 1, 4 or 8 bit image using palette as its color table
+	fn remap_to_palette(&self, palette: &[Rgb], bpp: u16, dither: Dither) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let width = self.get_width();
+		let height = self.get_height();
+		let mut result = Vec::<u8>::new_indexed_bitmap(width, height, bpp, palette)?;
+		let mut pixels = read_pixels(self)?;
+
+		let mut cache: HashMap<Rgb, u8> = HashMap::new();
+		let mut nearest = |value: [f64; 3]| -> u8 {
+			let to_u8 = |channel: f64| channel.round().clamp(0.0, 255.0) as u8;
+			let color = Rgb { r: to_u8(value[0]), g: to_u8(value[1]), b: to_u8(value[2]) };
+			*cache.entry(color).or_insert_with(|| nearest_palette_index(palette, &color))
+		};
+
+		// Error diffusion neighbours as (dx, rows further down, weight). Rows are visited from the top of the image.
+		let diffusion: &[(i64, i64, f64)] = match dither {
+			Dither::FloydSteinberg => &[(1, 0, 7.0 / 16.0), (-1, 1, 3.0 / 16.0), (0, 1, 5.0 / 16.0), (1, 1, 1.0 / 16.0)],
+			Dither::Atkinson => &[(1, 0, 0.125), (2, 0, 0.125), (-1, 1, 0.125), (0, 1, 0.125), (1, 1, 0.125), (0, 2, 0.125)],
+			_ => &[],
+		};
+		let bayer = match dither {
+			Dither::Bayer2 => Some(bayer_matrix(2)),
+			Dither::Bayer4 => Some(bayer_matrix(4)),
+			Dither::Bayer8 => Some(bayer_matrix(8)),
+			_ => None,
+		};
+		// Threshold offsets span the distance between neighbouring palette levels, assuming the entries form a rough cube
+		let levels = (palette.len() as f64).cbrt();
+		let spread = (255.0 / (levels - 1.0).max(1.0)).min(255.0);
+
+		for y in (0..height).rev() {
+			for x in 0..width {
+				let index = (y * width + x) as usize;
+				let mut value = pixels[index];
+				if let Some(matrix) = &bayer {
+					let n = matrix.len();
+					let threshold = (matrix[(height - 1 - y) as usize % n][x as usize % n] + 0.5) / (n * n) as f64 - 0.5;
+					for channel in value.iter_mut() {
+						*channel += threshold * spread;
+					}
+				}
+				let entry = nearest(value);
+				result.set_pixel_index(&Point { x, y }, entry);
+
+				let chosen = palette[entry as usize];
+				let error = [value[0] - chosen.r as f64, value[1] - chosen.g as f64, value[2] - chosen.b as f64];
+				for &(dx, down, weight) in diffusion {
+					let nx = x as i64 + dx;
+					let ny = y as i64 - down;
+					if nx < 0 || ny < 0 || nx >= width as i64 {
+						continue;
+					}
+					let neighbour = &mut pixels[(ny as u32 * width + nx as u32) as usize];
+					for channel in 0..3 {
+						neighbour[channel] += error[channel] * weight;
+					}
+				}
+			}
+		}
+		Ok(result)
+	}
+
+	// Reduces the image to the 2, 16 or 256 colors a 1, 4 or 8 bit image can hold
+	fn quantize(&self, bpp: u16, method: QuantizeMethod, dither: Dither) -> Result<Vec<u8>, String> {
+		if !matches!(bpp, 1 | 4 | 8) {
+			return Err(format!("Cannot quantize to {} bits per pixel, use 1, 4 or 8", bpp));
+		}
+		let palette = self.generate_palette(1 << bpp, method)?;
+		if palette.is_empty() {
+			return Err("Image has no pixels".to_string());
+		}
+		self.remap_to_palette(&palette, bpp, dither)
+	}
+}
+
+#[test]
+fn test_quantize_methods() {
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let green = Rgb {r: 0, g: 160, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(12, 6, 24);
+	bmp.draw_circle(&Point {x: 0, y: 0}, 5, &red);
+	bmp.draw_circle(&Point {x: 11, y: 5}, 4, &green);
+
+	// Three colors fit in a 4 bit palette exactly
+	for method in [QuantizeMethod::MedianCut, QuantizeMethod::Octree, QuantizeMethod::KMeans { iterations: 10 }] {
+		let indexed = bmp.quantize(4, method, Dither::FloydSteinberg).unwrap();
+		assert_eq!(indexed.get_bits_per_pixel(), 4);
+		assert_eq!(indexed.get_palette().len(), 3);
+		assert_eq!(indexed.get_file_size(), indexed.len() as u32);
+		for y in 0..6 {
+			for x in 0..12 {
+				assert_eq!(indexed.get_pixel(&Point {x, y}).unwrap(), bmp.get_pixel(&Point {x, y}).unwrap());
+			}
+		}
+	}
+
+	// Reducing to two colors still tells black and red apart
+	for method in [QuantizeMethod::MedianCut, QuantizeMethod::Octree, QuantizeMethod::KMeans { iterations: 10 }] {
+		let palette = bmp.generate_palette(2, method).unwrap();
+		assert_eq!(palette.len(), 2);
+		assert_ne!(nearest_palette_index(&palette, &Rgb {r: 0, g: 0, b: 0}), nearest_palette_index(&palette, &red));
+	}
+	assert!(bmp.quantize(24, QuantizeMethod::Octree, Dither::None).is_err());
+}
+
+#[test]
+fn test_dithering_preserves_tone() {
+	use crate::stats::Statistics;
+	// A flat mid gray rendered with only black and white
+	let gray = Rgb {r: 128, g: 128, b: 128};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(16, 16, 24);
+	bmp.draw_circle(&Point {x: 8, y: 8}, 30, &gray);
+	let palette = [Rgb {r: 0, g: 0, b: 0}, Rgb {r: 255, g: 255, b: 255}];
+
+	let flat = bmp.remap_to_palette(&palette, 1, Dither::None).unwrap();
+	assert_eq!(flat.get_row_size(), 4);
+	assert_eq!(flat.unique_colors().unwrap(), 1);
+	for dither in [Dither::FloydSteinberg, Dither::Atkinson, Dither::Bayer2, Dither::Bayer4, Dither::Bayer8] {
+		let dithered = bmp.remap_to_palette(&palette, 1, dither).unwrap();
+		let mean = dithered.statistics().unwrap().red.mean;
+		assert!((mean - 128.0).abs() < 16.0, "{:?} gave mean {}", dither, mean);
+	}
+}
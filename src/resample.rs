@@ -0,0 +1,215 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::indexed::new_bitmap_like;
+
+// Reconstruction filters for resizing, from fastest to sharpest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResizeFilter {
+	Nearest,
+	Bilinear,
+	// Keys cubic with a = -0.5 (Catmull-Rom)
+	Bicubic,
+	// Mitchell-Netravali cubic with B = C = 1/3
+	Mitchell,
+	Lanczos3,
+}
+
+impl ResizeFilter {
+	// Distance from the center beyond which the filter weight is zero
+	fn support(&self) -> f64 {
+		match self {
+			ResizeFilter::Nearest => 0.5,
+			ResizeFilter::Bilinear => 1.0,
+			ResizeFilter::Bicubic | ResizeFilter::Mitchell => 2.0,
+			ResizeFilter::Lanczos3 => 3.0,
+		}
+	}
+
+	fn weight(&self, x: f64) -> f64 {
+		let x = x.abs();
+		match self {
+			ResizeFilter::Nearest => if x <= 0.5 { 1.0 } else { 0.0 },
+			ResizeFilter::Bilinear => (1.0 - x).max(0.0),
+			ResizeFilter::Bicubic => cubic_weight(x, 0.0, 0.5),
+			ResizeFilter::Mitchell => cubic_weight(x, 1.0 / 3.0, 1.0 / 3.0),
+			ResizeFilter::Lanczos3 => {
+				if x < 1e-8 {
+					1.0
+				} else if x < 3.0 {
+					let pi_x = std::f64::consts::PI * x;
+					3.0 * pi_x.sin() * (pi_x / 3.0).sin() / (pi_x * pi_x)
+				} else {
+					0.0
+				}
+			}
+		}
+	}
+}
+
+// The BC-spline family of cubic filters, see Mitchell & Netravali, "Reconstruction Filters in Computer Graphics"
+fn cubic_weight(x: f64, b: f64, c: f64) -> f64 {
+	if x < 1.0 {
+		((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+	} else if x < 2.0 {
+		((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+	} else {
+		0.0
+	}
+}
+
+// Source indices and normalized weights contributing to one destination pixel
+struct Contribution {
+	first: usize,
+	weights: Vec<f64>,
+}
+
+// Works out, for each of the destination_size output pixels, which source pixels contribute and by how much.
+// When shrinking, the filter is stretched so every source pixel is taken into account, except for Nearest,
+// which always picks the single source pixel under the destination pixel's center.
+fn contributions(source_size: u32, destination_size: u32, filter: ResizeFilter) -> Vec<Contribution> {
+	let scale = source_size as f64 / destination_size as f64;
+	let filter_scale = scale.max(1.0);
+	let support = filter.support() * filter_scale;
+	let mut result = Vec::with_capacity(destination_size as usize);
+	for i in 0..destination_size {
+		let center = (i as f64 + 0.5) * scale;
+		if filter == ResizeFilter::Nearest {
+			let index = (center.floor() as usize).min(source_size as usize - 1);
+			result.push(Contribution { first: index, weights: vec![1.0] });
+			continue;
+		}
+		let first = ((center - support).floor().max(0.0)) as usize;
+		let last = ((center + support).ceil() as usize).min(source_size as usize);
+		let mut weights: Vec<f64> = (first..last).map(|j| filter.weight((j as f64 + 0.5 - center) / filter_scale)).collect();
+		let total: f64 = weights.iter().sum();
+		if total.abs() > 1e-12 {
+			for weight in weights.iter_mut() {
+				*weight /= total;
+			}
+		} else {
+			// No tap landed inside the filter, fall back to the nearest source pixel
+			let index = (center.floor() as usize).min(source_size as usize - 1);
+			result.push(Contribution { first: index, weights: vec![1.0] });
+			continue;
+		}
+		result.push(Contribution { first, weights });
+	}
+	result
+}
+
+// Reads every pixel into a row-major list of [r, g, b] values
+pub(crate) fn read_pixels(bmp: &Vec<u8>) -> Result<Vec<[f64; 3]>, String> {
+	let width = bmp.get_width();
+	let height = bmp.get_height();
+	let mut pixels = Vec::with_capacity(width as usize * height as usize);
+	for y in 0..height {
+		for x in 0..width {
+			let pixel = bmp.get_pixel(&Point { x, y })?;
+			pixels.push([pixel.r as f64, pixel.g as f64, pixel.b as f64]);
+		}
+	}
+	Ok(pixels)
+}
+
+// Writes a row-major list of [r, g, b] values into a new bitmap with the depth and palette of like,
+// rounding and clamping each channel
+pub(crate) fn write_pixels(pixels: &[[f64; 3]], width: u32, height: u32, like: &Vec<u8>) -> Vec<u8> {
+	let mut bmp = new_bitmap_like(like, width, height);
+	let to_u8 = |value: f64| -> u8 { value.round().clamp(0.0, 255.0) as u8 };
+	for y in 0..height {
+		for x in 0..width {
+			let pixel = pixels[y as usize * width as usize + x as usize];
+			bmp.draw_point(&Point { x, y }, &Rgb { r: to_u8(pixel[0]), g: to_u8(pixel[1]), b: to_u8(pixel[2]) });
+		}
+	}
+	bmp
+}
+
+pub trait Resample {
+	fn resize(&self, width: u32, height: u32, filter: ResizeFilter) -> Result<Vec<u8>, String>;
+}
+
+impl Resample for Vec<u8> {
+	// Returns a new bitmap of width x height, filtering horizontally then vertically
+	fn resize(&self, width: u32, height: u32, filter: ResizeFilter) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let source_width = self.get_width();
+		let source_height = self.get_height();
+		if width == 0 || height == 0 || source_width == 0 || source_height == 0 {
+			return Err(format!("Cannot resize a {}x{} image to {}x{}", source_width, source_height, width, height));
+		}
+		let source = read_pixels(self)?;
+
+		// Horizontal pass: source_height rows of the new width
+		let horizontal = contributions(source_width, width, filter);
+		let mut intermediate = vec![[0.0; 3]; width as usize * source_height as usize];
+		for y in 0..source_height as usize {
+			let row = &source[y * source_width as usize..(y + 1) * source_width as usize];
+			for (x, contribution) in horizontal.iter().enumerate() {
+				let mut sum = [0.0; 3];
+				for (k, weight) in contribution.weights.iter().enumerate() {
+					let pixel = row[contribution.first + k];
+					for channel in 0..3 {
+						sum[channel] += pixel[channel] * weight;
+					}
+				}
+				intermediate[y * width as usize + x] = sum;
+			}
+		}
+
+		// Vertical pass
+		let vertical = contributions(source_height, height, filter);
+		let mut output = vec![[0.0; 3]; width as usize * height as usize];
+		for (y, contribution) in vertical.iter().enumerate() {
+			for x in 0..width as usize {
+				let mut sum = [0.0; 3];
+				for (k, weight) in contribution.weights.iter().enumerate() {
+					let pixel = intermediate[(contribution.first + k) * width as usize + x];
+					for channel in 0..3 {
+						sum[channel] += pixel[channel] * weight;
+					}
+				}
+				output[y * width as usize + x] = sum;
+			}
+		}
+
+		Ok(write_pixels(&output, width, height, self))
+	}
+}
+
+#[test]
+fn test_resize_filters() {
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let blue = Rgb {r: 0, g: 0, b: 255};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(8, 4, 24);
+	for y in 0..4 {
+		bmp.draw_line(&Point {x: 0, y}, &Point {x: 3, y}, &red);
+		bmp.draw_line(&Point {x: 4, y}, &Point {x: 7, y}, &blue);
+	}
+
+	// Every filter keeps a uniform area uniform and the header correct
+	for filter in [ResizeFilter::Nearest, ResizeFilter::Bilinear, ResizeFilter::Bicubic, ResizeFilter::Mitchell, ResizeFilter::Lanczos3] {
+		let large = bmp.resize(16, 9, filter).unwrap();
+		assert_eq!(large.get_width(), 16);
+		assert_eq!(large.get_height(), 9);
+		assert_eq!(large.get_file_size(), large.len() as u32);
+		assert_eq!(large.get_pixel(&Point {x: 0, y: 4}).unwrap(), red);
+		assert_eq!(large.get_pixel(&Point {x: 15, y: 4}).unwrap(), blue);
+	}
+
+	// Shrinking to a single pixel averages everything
+	let tiny = bmp.resize(1, 1, ResizeFilter::Bilinear).unwrap();
+	assert_eq!(tiny.get_pixel(&Point {x: 0, y: 0}).unwrap(), Rgb {r: 128, g: 0, b: 128});
+
+	// Nearest only ever returns source colors, also when shrinking
+	let nearest = bmp.resize(2, 1, ResizeFilter::Nearest).unwrap();
+	assert_eq!(nearest.get_pixel(&Point {x: 0, y: 0}).unwrap(), red);
+	assert_eq!(nearest.get_pixel(&Point {x: 1, y: 0}).unwrap(), blue);
+	let nearest = bmp.resize(1, 1, ResizeFilter::Nearest).unwrap().get_pixel(&Point {x: 0, y: 0}).unwrap();
+	assert!(nearest == red || nearest == blue);
+	assert!(bmp.resize(0, 1, ResizeFilter::Nearest).is_err());
+}
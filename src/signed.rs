@@ -0,0 +1,208 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Rect;
+use crate::clip::draw_clipped_line;
+
+// A position that may lie left of or above the image, for shapes that are only partly visible
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignedPoint {
+	pub x: i32,
+	pub y: i32
+}
+
+// A sub-pixel position. Pixel (x, y) covers the area from (x, y) to (x + 1, y + 1).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatPoint {
+	pub x: f64,
+	pub y: f64
+}
+
+impl From<&Point> for SignedPoint {
+	fn from(point: &Point) -> SignedPoint {
+		SignedPoint { x: point.x.min(i32::MAX as u32) as i32, y: point.y.min(i32::MAX as u32) as i32 }
+	}
+}
+
+impl From<&FloatPoint> for SignedPoint {
+	// The pixel whose area contains the point, saturating at the i32 range
+	fn from(point: &FloatPoint) -> SignedPoint {
+		SignedPoint { x: point.x.floor() as i32, y: point.y.floor() as i32 }
+	}
+}
+
+pub trait SignedDraw {
+	fn draw_signed_point(&mut self, point: &SignedPoint, color: &Rgb);
+	fn draw_signed_line(&mut self, start: &SignedPoint, end: &SignedPoint, color: &Rgb);
+	fn draw_signed_circle(&mut self, center: &SignedPoint, radius: u32, color: &Rgb);
+	fn draw_signed_rectangle(&mut self, point1: &SignedPoint, point2: &SignedPoint, color: &Rgb);
+	fn draw_signed_polyline(&mut self, points: &[SignedPoint], color: &Rgb);
+	fn draw_signed_polygon(&mut self, points: &[SignedPoint], color: &Rgb);
+	fn draw_float_line(&mut self, start: &FloatPoint, end: &FloatPoint, color: &Rgb);
+	fn draw_float_circle(&mut self, center: &FloatPoint, radius: f64, color: &Rgb);
+	fn draw_float_polyline(&mut self, points: &[FloatPoint], color: &Rgb);
+}
+
+// Calls plot for every pixel on the line from (x0, y0) to (x1, y1) using Bresenham's algorithm.
+// Works in i64 so no intermediate value can wrap.
+pub(crate) fn bresenham<F: FnMut(i64, i64)>(x0: i64, y0: i64, x1: i64, y1: i64, mut plot: F) {
+	let dx = (x1 - x0).abs();
+	let dy = (y1 - y0).abs();
+	let sx = if x0 < x1 { 1 } else { -1 };
+	let sy = if y0 < y1 { 1 } else { -1 };
+	let mut err = dx - dy;
+	let mut x = x0;
+	let mut y = y0;
+	loop {
+		plot(x, y);
+		if x == x1 && y == y1 {
+			break;
+		}
+		let e2 = 2 * err;
+		if e2 > -dy {
+			err -= dy;
+			x += sx;
+		}
+		if e2 < dx {
+			err += dx;
+			y += sy;
+		}
+	}
+}
+
+// Calls span(y, x_start, x_end) for every row of pixels within radius of (cx, cy), restricted to clip. x_end is inclusive.
+pub(crate) fn disk_spans<F: FnMut(u32, u32, u32)>(cx: f64, cy: f64, radius: f64, clip: &Rect, mut span: F) {
+	if clip.is_empty() || radius < 0.0 {
+		return;
+	}
+	let min_x = ((cx - radius).floor() as i64).max(clip.x as i64);
+	let max_x = ((cx + radius).ceil() as i64).min(clip.x as i64 + clip.width as i64 - 1);
+	let min_y = ((cy - radius).floor() as i64).max(clip.y as i64);
+	let max_y = ((cy + radius).ceil() as i64).min(clip.y as i64 + clip.height as i64 - 1);
+	for y in min_y..=max_y {
+		let dy = y as f64 - cy;
+		let half_width_squared = radius * radius - dy * dy;
+		if half_width_squared < 0.0 {
+			continue;
+		}
+		let half_width = half_width_squared.sqrt();
+		let start = ((cx - half_width).ceil() as i64).max(min_x);
+		let end = ((cx + half_width).floor() as i64).min(max_x);
+		if end >= start {
+			span(y as u32, start as u32, end as u32);
+		}
+	}
+}
+
+// Fills every pixel within radius of (cx, cy), visiting only the part of the bounding box inside the clip rectangle
+pub(crate) fn fill_disk<T: Bitmap + ?Sized>(bmp: &mut T, cx: f64, cy: f64, radius: f64, color: &Rgb) {
+	let clip = bmp.clip_rect();
+	disk_spans(cx, cy, radius, &clip, |y, x_start, x_end| {
+		for x in x_start..=x_end {
+			bmp.draw_point(&Point { x, y }, color);
+		}
+	});
+}
+
+impl<T: Bitmap> SignedDraw for T {
+	fn draw_signed_point(&mut self, point: &SignedPoint, color: &Rgb) {
+		if point.x < 0 || point.y < 0 {
+			return;
+		}
+		self.draw_point(&Point { x: point.x as u32, y: point.y as u32 }, color);
+	}
+
+	fn draw_signed_line(&mut self, start: &SignedPoint, end: &SignedPoint, color: &Rgb) {
+		draw_clipped_line(self, start.x as i64, start.y as i64, end.x as i64, end.y as i64, color);
+	}
+
+	fn draw_signed_circle(&mut self, center: &SignedPoint, radius: u32, color: &Rgb) {
+		fill_disk(self, center.x as f64, center.y as f64, radius as f64, color);
+	}
+
+	// Input two opposite corners of the rectangle
+	fn draw_signed_rectangle(&mut self, point1: &SignedPoint, point2: &SignedPoint, color: &Rgb) {
+		let point3 = SignedPoint { x: point1.x, y: point2.y };
+		let point4 = SignedPoint { x: point2.x, y: point1.y };
+		self.draw_signed_line(point1, &point3, color);
+		self.draw_signed_line(&point3, point2, color);
+		self.draw_signed_line(point2, &point4, color);
+		self.draw_signed_line(&point4, point1, color);
+	}
+
+	fn draw_signed_polyline(&mut self, points: &[SignedPoint], color: &Rgb) {
+		match points.len() {
+			0 => {}
+			1 => self.draw_signed_point(&points[0], color),
+			_ => {
+				for pair in points.windows(2) {
+					self.draw_signed_line(&pair[0], &pair[1], color);
+				}
+			}
+		}
+	}
+
+	fn draw_signed_polygon(&mut self, points: &[SignedPoint], color: &Rgb) {
+		if points.is_empty() {
+			return;
+		}
+		self.draw_signed_polyline(points, color);
+		self.draw_signed_line(&points[0], &points[points.len()-1], color);
+	}
+
+	fn draw_float_line(&mut self, start: &FloatPoint, end: &FloatPoint, color: &Rgb) {
+		self.draw_signed_line(&SignedPoint::from(start), &SignedPoint::from(end), color);
+	}
+
+	// Fills the pixels whose centers lie inside the circle, so the center and radius need not be whole numbers
+	fn draw_float_circle(&mut self, center: &FloatPoint, radius: f64, color: &Rgb) {
+		fill_disk(self, center.x - 0.5, center.y - 0.5, radius, color);
+	}
+
+	fn draw_float_polyline(&mut self, points: &[FloatPoint], color: &Rgb) {
+		let points: Vec<SignedPoint> = points.iter().map(SignedPoint::from).collect();
+		self.draw_signed_polyline(&points, color);
+	}
+}
+
+#[test]
+fn test_signed_circle_clipping() {
+	use crate::Get;
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(20, 20, 24);
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let black = Rgb {r: 0, g: 0, b: 0};
+
+	// Only the right half of this circle is visible
+	bmp.draw_signed_circle(&SignedPoint {x: -5, y: 10}, 8, &red);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 10}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 10}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 4, y: 10}).unwrap(), black);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 17}).unwrap(), black);
+
+	// A line entering from outside the top left corner
+	bmp.draw_signed_line(&SignedPoint {x: -10, y: -10}, &SignedPoint {x: 19, y: 19}, &red);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 0}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 19, y: 19}).unwrap(), red);
+}
+
+#[test]
+fn test_float_coordinates_near_pixel_boundaries() {
+	use crate::Get;
+	use crate::path::*;
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(4, 4, 24);
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let black = Rgb {r: 0, g: 0, b: 0};
+
+	// (0.9, 0.9) still lies inside pixel (0, 0)
+	assert_eq!(SignedPoint::from(&FloatPoint {x: 0.9, y: 0.9}), SignedPoint {x: 0, y: 0});
+	assert_eq!(SignedPoint::from(&FloatPoint {x: 1.0, y: -0.1}), SignedPoint {x: 1, y: -1});
+	bmp.draw_float_line(&FloatPoint {x: 0.9, y: 0.9}, &FloatPoint {x: 0.9, y: 0.9}, &red);
+	assert_eq!(bmp.get_pixel(&Point {x: 0, y: 0}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 1, y: 1}).unwrap(), black);
+
+	// Stroked paths follow the same convention
+	let path = Path::new().move_to(0.5, 2.9).line_to(3.5, 2.9);
+	bmp.stroke_path(&path, &red);
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 2}).unwrap(), red);
+	assert_eq!(bmp.get_pixel(&Point {x: 3, y: 3}).unwrap(), black);
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::adjust::LumaWeights;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+	Red,
+	Green,
+	Blue,
+	// Rec. 601 weighted brightness
+	Luma,
+}
+
+// Number of pixels with each value, per channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+	pub red: [u32; 256],
+	pub green: [u32; 256],
+	pub blue: [u32; 256],
+	pub luma: [u32; 256],
+}
+
+impl Histogram {
+	pub fn channel(&self, channel: Channel) -> &[u32; 256] {
+		match channel {
+			Channel::Red => &self.red,
+			Channel::Green => &self.green,
+			Channel::Blue => &self.blue,
+			Channel::Luma => &self.luma,
+		}
+	}
+
+	// Summary of one channel, or None for an empty image
+	pub fn channel_stats(&self, channel: Channel) -> Option<ChannelStats> {
+		let counts = self.channel(channel);
+		let total: u64 = counts.iter().map(|&count| count as u64).sum();
+		if total == 0 {
+			return None;
+		}
+		let min = counts.iter().position(|&count| count > 0)? as u8;
+		let max = counts.iter().rposition(|&count| count > 0)? as u8;
+		let sum: f64 = counts.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum();
+		let mean = sum / total as f64;
+		let variance: f64 = counts.iter().enumerate().map(|(value, &count)| (value as f64 - mean).powi(2) * count as f64).sum::<f64>() / total as f64;
+		Some(ChannelStats { min, max, mean, stddev: variance.sqrt() })
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelStats {
+	pub min: u8,
+	pub max: u8,
+	pub mean: f64,
+	// Population standard deviation
+	pub stddev: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageStats {
+	pub red: ChannelStats,
+	pub green: ChannelStats,
+	pub blue: ChannelStats,
+	pub luma: ChannelStats,
+}
+
+// Calls visit with the color of every pixel, row by row from the bottom.
+// 24 and 32 bit rows are read straight from the pixel array, skipping the padding at the end of each scan line.
+pub(crate) fn for_each_pixel<F: FnMut(Rgb)>(bmp: &Vec<u8>, mut visit: F) -> Result<(), String> {
+	if !bmp.has_file_signature() {
+		return Err("Invalid file signature. Not a Bitmap.".to_string());
+	}
+	let width = bmp.get_width() as usize;
+	let height = bmp.get_height() as usize;
+	let bits_per_pixel = bmp.get_bits_per_pixel();
+	if bits_per_pixel == 24 || bits_per_pixel == 32 {
+		let bytes_per_pixel = bits_per_pixel as usize / 8;
+		let row_size = width * bytes_per_pixel + bmp.get_padding_per_line() as usize;
+		let pixel_array = bmp.get_pixel_array_offset();
+		if bmp.len() < pixel_array + row_size * height {
+			return Err("Pixel array is shorter than the header describes".to_string());
+		}
+		for row in bmp[pixel_array..pixel_array + row_size * height].chunks_exact(row_size) {
+			for pixel in row[..width * bytes_per_pixel].chunks_exact(bytes_per_pixel) {
+				visit(Rgb { r: pixel[2], g: pixel[1], b: pixel[0] });
+			}
+		}
+	} else {
+		for y in 0..height as u32 {
+			for x in 0..width as u32 {
+				visit(bmp.get_pixel(&Point { x, y })?);
+			}
+		}
+	}
+	Ok(())
+}
+
+pub trait Statistics {
+	fn histogram(&self) -> Result<Histogram, String>;
+	fn statistics(&self) -> Result<ImageStats, String>;
+	fn unique_colors(&self) -> Result<usize, String>;
+	fn dominant_colors(&self, count: usize) -> Result<Vec<(Rgb, u32)>, String>;
+}
+
+impl Statistics for Vec<u8> {
+	fn histogram(&self) -> Result<Histogram, String> {
+		let mut histogram = Histogram { red: [0; 256], green: [0; 256], blue: [0; 256], luma: [0; 256] };
+		for_each_pixel(self, |color| {
+			histogram.red[color.r as usize] += 1;
+			histogram.green[color.g as usize] += 1;
+			histogram.blue[color.b as usize] += 1;
+			histogram.luma[LumaWeights::Rec601.luma(&color).round() as usize] += 1;
+		})?;
+		Ok(histogram)
+	}
+
+	fn statistics(&self) -> Result<ImageStats, String> {
+		let histogram = self.histogram()?;
+		let stats = |channel| histogram.channel_stats(channel).ok_or_else(|| "Image has no pixels".to_string());
+		Ok(ImageStats {
+			red: stats(Channel::Red)?,
+			green: stats(Channel::Green)?,
+			blue: stats(Channel::Blue)?,
+			luma: stats(Channel::Luma)?,
+		})
+	}
+
+	fn unique_colors(&self) -> Result<usize, String> {
+		let mut colors = HashSet::new();
+		for_each_pixel(self, |color| {
+			colors.insert(color);
+		})?;
+		Ok(colors.len())
+	}
+
+	// Returns up to count colors with the number of pixels they cover, most common first.
+	// Similar shades are grouped into buckets of 8 values per channel and reported as their average, so noise and
+	// anti-aliasing do not split one visual color into many.
+	fn dominant_colors(&self, count: usize) -> Result<Vec<(Rgb, u32)>, String> {
+		let mut buckets: HashMap<(u8, u8, u8), (u32, [u64; 3])> = HashMap::new();
+		for_each_pixel(self, |color| {
+			let bucket = buckets.entry((color.r >> 3, color.g >> 3, color.b >> 3)).or_insert((0, [0; 3]));
+			bucket.0 += 1;
+			bucket.1[0] += color.r as u64;
+			bucket.1[1] += color.g as u64;
+			bucket.1[2] += color.b as u64;
+		})?;
+		let mut dominant: Vec<(Rgb, u32)> = buckets.values().map(|(pixels, sums)| {
+			let average = |sum: u64| ((sum + *pixels as u64 / 2) / *pixels as u64) as u8;
+			(Rgb { r: average(sums[0]), g: average(sums[1]), b: average(sums[2]) }, *pixels)
+		}).collect();
+		// Ties are ordered by color so the result does not depend on hash order
+		dominant.sort_by(|a, b| b.1.cmp(&a.1).then((a.0.r, a.0.g, a.0.b).cmp(&(b.0.r, b.0.g, b.0.b))));
+		dominant.truncate(count);
+		Ok(dominant)
+	}
+}
+
+#[test]
+fn test_histogram_and_statistics() {
+	// Width 3 at 24 bits leaves 3 bytes of padding on each row, which must not be counted
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(3, 2, 24);
+	bmp.draw_point(&Point {x: 0, y: 0}, &Rgb {r: 255, g: 0, b: 0});
+	bmp.draw_point(&Point {x: 2, y: 1}, &Rgb {r: 255, g: 255, b: 255});
+
+	let histogram = bmp.histogram().unwrap();
+	assert_eq!(histogram.red[255], 2);
+	assert_eq!(histogram.red[0], 4);
+	assert_eq!(histogram.green[255], 1);
+	assert_eq!(histogram.luma[76], 1);
+	assert_eq!(histogram.luma.iter().sum::<u32>(), 6);
+
+	let stats = bmp.statistics().unwrap();
+	assert_eq!((stats.red.min, stats.red.max), (0, 255));
+	assert!((stats.red.mean - 85.0).abs() < 1e-9);
+	assert!((stats.red.stddev - 120.208).abs() < 1e-3);
+	assert_eq!(stats.blue.mean, 42.5);
+}
+
+#[test]
+fn test_unique_and_dominant_colors() {
+	let red = Rgb {r: 250, g: 0, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(10, 10, 32);
+	bmp.draw_circle(&Point {x: 0, y: 0}, 4, &red);
+	bmp.draw_point(&Point {x: 9, y: 9}, &Rgb {r: 252, g: 2, b: 1});
+	bmp.draw_point(&Point {x: 8, y: 9}, &Rgb {r: 0, g: 0, b: 255});
+
+	assert_eq!(bmp.unique_colors().unwrap(), 4);
+	let dominant = bmp.dominant_colors(2).unwrap();
+	assert_eq!(dominant.len(), 2);
+	assert_eq!(dominant[0].0, Rgb {r: 0, g: 0, b: 0});
+	// The near-red pixel is grouped with the red quarter disk
+	assert_eq!(dominant[1].0.g, 0);
+	assert_eq!(dominant.iter().map(|(_, pixels)| pixels).sum::<u32>() + 1, 100);
+	assert!(vec![0u8; 4].histogram().is_err());
+}
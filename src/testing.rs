@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::PathBuf;
+use crate::Get;
+use crate::compare::*;
+use crate::constants::*;
+
+// Set to 1 to overwrite the stored references with the images being tested
+pub const UPDATE_SNAPSHOTS_VAR: &str = "UPDATE_BITMAP_SNAPSHOTS";
+// Overrides the directory the references are stored in
+pub const SNAPSHOT_DIR_VAR: &str = "BITMAP_SNAPSHOT_DIR";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotOptions {
+	pub directory: PathBuf,
+	// Largest per channel difference still counted as a match
+	pub tolerance: u8,
+	// Write the image as the new reference instead of comparing
+	pub update: bool,
+}
+
+impl SnapshotOptions {
+	// Uses default_directory unless the environment overrides it, and reads the update flag from the environment
+	pub fn from_env(default_directory: &str, tolerance: u8) -> SnapshotOptions {
+		let directory = std::env::var(SNAPSHOT_DIR_VAR).map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(default_directory));
+		let update = std::env::var(UPDATE_SNAPSHOTS_VAR).map(|value| value == "1" || value == "true").unwrap_or(false);
+		SnapshotOptions { directory, tolerance, update }
+	}
+}
+
+// True when the file is long enough for the headers and for the pixel array they describe,
+// so reading the header fields and pixels cannot go out of bounds
+fn is_complete_bitmap(bmp: &[u8]) -> bool {
+	if bmp.len() < TOTAL_HEADER_SIZE as usize || !bmp.starts_with(b"BM") {
+		return false;
+	}
+	let field = |offset: usize| u32::from_le_bytes([bmp[offset], bmp[offset + 1], bmp[offset + 2], bmp[offset + 3]]) as u64;
+	let width = field(HEADER_WIDTH_OFFSET);
+	let height = field(HEADER_HEIGHT_OFFSET);
+	let row_size = (width * bmp[HEADER_BITS_PER_PIXEL] as u64).div_ceil(32) * 4;
+	let image_size = (row_size * height).max(field(HEADER_IMAGE_SIZE));
+	field(HEADER_PIXEL_ARRAY_OFFSET) + image_size <= bmp.len() as u64
+}
+
+// Compares bmp with the reference <directory>/<name>.bmp.
+// On a mismatch <name>.actual.bmp and <name>.diff.bmp are written next to the reference and an error describes the failure.
+// Bitmaps are passed around as &Vec<u8> throughout the crate
+#[allow(clippy::ptr_arg)]
+pub fn check_snapshot(bmp: &Vec<u8>, name: &str, options: &SnapshotOptions) -> Result<(), String> {
+	let reference_path = options.directory.join(format!("{}.bmp", name));
+	let actual_path = options.directory.join(format!("{}.actual.bmp", name));
+	let diff_path = options.directory.join(format!("{}.diff.bmp", name));
+	fs::create_dir_all(&options.directory).map_err(|error| format!("Cannot create {}: {}", options.directory.display(), error))?;
+
+	if options.update {
+		fs::write(&reference_path, bmp).map_err(|error| format!("Cannot write {}: {}", reference_path.display(), error))?;
+		let _ = fs::remove_file(&actual_path);
+		let _ = fs::remove_file(&diff_path);
+		return Ok(());
+	}
+
+	let write_actual = || fs::write(&actual_path, bmp).map_err(|error| format!("Cannot write {}: {}", actual_path.display(), error));
+	let reference = match fs::read(&reference_path) {
+		Ok(reference) => reference,
+		Err(_) => {
+			write_actual()?;
+			return Err(format!("No snapshot {} yet, the image was written to {}. Rerun with {}=1 to accept it.", reference_path.display(), actual_path.display(), UPDATE_SNAPSHOTS_VAR));
+		}
+	};
+
+	if !is_complete_bitmap(&reference) {
+		write_actual()?;
+		return Err(format!("Snapshot {} is not a complete bitmap, see {}", reference_path.display(), actual_path.display()));
+	}
+	if reference.get_width() != bmp.get_width() || reference.get_height() != bmp.get_height() {
+		write_actual()?;
+		return Err(format!("Snapshot {} is {}x{} but the image is {}x{}, see {}", reference_path.display(), reference.get_width(), reference.get_height(), bmp.get_width(), bmp.get_height(), actual_path.display()));
+	}
+
+	let differences = reference.count_differences(bmp, options.tolerance)?;
+	if differences > 0 {
+		write_actual()?;
+		let diff = reference.diff_image(bmp, options.tolerance)?;
+		fs::write(&diff_path, diff).map_err(|error| format!("Cannot write {}: {}", diff_path.display(), error))?;
+		return Err(format!("{} pixels differ from snapshot {} by more than {}, see {} and {}. Rerun with {}=1 to accept the change.", differences, reference_path.display(), options.tolerance, actual_path.display(), diff_path.display(), UPDATE_SNAPSHOTS_VAR));
+	}
+
+	// A passing run cleans up after an earlier failure
+	let _ = fs::remove_file(&actual_path);
+	let _ = fs::remove_file(&diff_path);
+	Ok(())
+}
+
+// Compares a bitmap with the reference tests/snapshots/<name>.bmp of the calling crate, optionally with a per channel tolerance.
+// Set UPDATE_BITMAP_SNAPSHOTS=1 to store the current images as the new references.
+#[macro_export]
+macro_rules! assert_bitmap_snapshot {
+	($bmp:expr, $name:expr) => {
+		$crate::assert_bitmap_snapshot!($bmp, $name, 0)
+	};
+	($bmp:expr, $name:expr, $tolerance:expr) => {{
+		let options = $crate::SnapshotOptions::from_env(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots"), $tolerance);
+		if let Err(message) = $crate::check_snapshot(&$bmp, $name, &options) {
+			panic!("{}", message);
+		}
+	}};
+}
+
+#[test]
+fn test_snapshot_lifecycle() {
+	use crate::Bitmap;
+	use crate::Point;
+	use crate::Rgb;
+	let directory = std::env::temp_dir().join(format!("rustic_bitmap_snapshots_{}", std::process::id()));
+	let mut options = SnapshotOptions { directory: directory.clone(), tolerance: 0, update: false };
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(8, 8, 24);
+	bmp.draw_circle(&Point {x: 4, y: 4}, 3, &Rgb {r: 255, g: 0, b: 0});
+
+	// Without a reference the test fails and leaves the actual image for review
+	assert!(check_snapshot(&bmp, "circle", &options).is_err());
+	assert_eq!(fs::read(directory.join("circle.actual.bmp")).unwrap(), bmp);
+
+	options.update = true;
+	check_snapshot(&bmp, "circle", &options).unwrap();
+	assert!(!directory.join("circle.actual.bmp").exists());
+	options.update = false;
+	check_snapshot(&bmp, "circle", &options).unwrap();
+
+	// A small change passes within tolerance and fails without it, writing a diff
+	bmp.draw_point(&Point {x: 0, y: 0}, &Rgb {r: 2, g: 0, b: 0});
+	check_snapshot(&bmp, "circle", &SnapshotOptions { tolerance: 2, ..options.clone() }).unwrap();
+	let message = check_snapshot(&bmp, "circle", &options).unwrap_err();
+	assert!(message.starts_with("1 pixels differ"));
+	assert!(directory.join("circle.diff.bmp").exists());
+	assert!(directory.join("circle.actual.bmp").exists());
+
+	// A truncated reference is reported instead of panicking
+	fs::write(directory.join("broken.bmp"), b"BM\0\0\0\0\0\0\0\0").unwrap();
+	let message = check_snapshot(&bmp, "broken", &options).unwrap_err();
+	assert!(message.contains("broken.bmp"));
+	let mut short = bmp.clone();
+	short.truncate(bmp.len() - 1);
+	fs::write(directory.join("short.bmp"), short).unwrap();
+	assert!(check_snapshot(&bmp, "short", &options).is_err());
+
+	let _ = fs::remove_dir_all(&directory);
+}
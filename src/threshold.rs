@@ -0,0 +1,151 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::adjust::LumaWeights;
+use crate::filter::*;
+use crate::indexed::*;
+use crate::resample::read_pixels;
+use crate::stats::*;
+
+// How the level separating black from white is chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdMethod {
+	// Pixels with a luma above the level become white
+	Fixed(u8),
+	// The level that best separates the luma histogram into two classes
+	Otsu,
+	// Compares each pixel with the mean of the (2 * radius + 1) square around it, minus offset.
+	// Copes with uneven lighting across a scanned page.
+	AdaptiveMean { radius: u32, offset: f64 },
+	// Like AdaptiveMean, with the neighbourhood weighted by a Gaussian of the given sigma
+	AdaptiveGaussian { sigma: f64, offset: f64 },
+}
+
+// Otsu's method: the level maximizing the variance between the pixels at or below it and those above
+pub fn otsu_level(counts: &[u32; 256]) -> u8 {
+	let total: f64 = counts.iter().map(|&count| count as f64).sum();
+	let weighted_total: f64 = counts.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum();
+	let mut best_level = 0;
+	let mut best_variance = -1.0;
+	let mut below = 0.0;
+	let mut weighted_below = 0.0;
+	for (level, &count) in counts.iter().enumerate() {
+		below += count as f64;
+		weighted_below += level as f64 * count as f64;
+		let above = total - below;
+		if below == 0.0 || above == 0.0 {
+			continue;
+		}
+		let mean_below = weighted_below / below;
+		let mean_above = (weighted_total - weighted_below) / above;
+		let variance = below * above * (mean_below - mean_above).powi(2);
+		if variance > best_variance {
+			best_variance = variance;
+			best_level = level;
+		}
+	}
+	best_level as u8
+}
+
+pub trait Threshold {
+	fn threshold(&self, method: ThresholdMethod) -> Result<Vec<u8>, String>;
+}
+
+impl Threshold for Vec<u8> {
+	// Returns a 1 bit image with a black and white palette
+	fn threshold(&self, method: ThresholdMethod) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let width = self.get_width();
+		let height = self.get_height();
+		let weights = LumaWeights::Rec601.weights();
+		let lumas: Vec<f64> = read_pixels(self)?.iter().map(|pixel| {
+			(weights[0] * pixel[0] + weights[1] * pixel[1] + weights[2] * pixel[2]).round()
+		}).collect();
+
+		// The level each pixel is compared against
+		let levels: Vec<f64> = match method {
+			ThresholdMethod::Fixed(level) => vec![level as f64; lumas.len()],
+			ThresholdMethod::Otsu => vec![otsu_level(&self.histogram()?.luma) as f64; lumas.len()],
+			ThresholdMethod::AdaptiveMean { radius, offset } => {
+				let size = 2 * radius as usize + 1;
+				local_means(&lumas, width, height, &vec![1.0 / size as f64; size], offset)
+			}
+			ThresholdMethod::AdaptiveGaussian { sigma, offset } => {
+				if sigma <= 0.0 {
+					return Err(format!("Gaussian sigma must be positive, got {}", sigma));
+				}
+				local_means(&lumas, width, height, &gaussian_weights(sigma), offset)
+			}
+		};
+
+		let palette = [Rgb { r: 0, g: 0, b: 0 }, Rgb { r: 255, g: 255, b: 255 }];
+		let mut result = Vec::<u8>::new_indexed_bitmap(width, height, 1, &palette)?;
+		for y in 0..height {
+			for x in 0..width {
+				let index = (y * width + x) as usize;
+				if lumas[index] > levels[index] {
+					result.set_pixel_index(&Point { x, y }, 1);
+				}
+			}
+		}
+		Ok(result)
+	}
+}
+
+// Smooths the luma values with a separable kernel and subtracts offset
+fn local_means(lumas: &[f64], width: u32, height: u32, weights: &[f64], offset: f64) -> Vec<f64> {
+	let gray: Vec<[f64; 3]> = lumas.iter().map(|&luma| [luma; 3]).collect();
+	convolve_separable(&gray, width, height, weights, EdgeMode::Mirror).iter().map(|mean| mean[0] - offset).collect()
+}
+
+#[test]
+fn test_fixed_and_otsu_threshold() {
+	use crate::adjust::Adjust;
+	let dark = Rgb {r: 40, g: 40, b: 40};
+	let light = Rgb {r: 200, g: 190, b: 180};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(10, 4, 24);
+	bmp.map_colors(None, |_| dark);
+	bmp.draw_circle(&Point {x: 9, y: 0}, 3, &light);
+
+	let binary = bmp.threshold(ThresholdMethod::Otsu).unwrap();
+	assert_eq!(binary.get_bits_per_pixel(), 1);
+	assert_eq!(binary.get_palette(), vec![Rgb {r: 0, g: 0, b: 0}, Rgb {r: 255, g: 255, b: 255}]);
+	assert_eq!(binary.get_file_size(), binary.len() as u32);
+	assert_eq!(binary.get_pixel_index(&Point {x: 9, y: 0}).unwrap(), 1);
+	assert_eq!(binary.get_pixel_index(&Point {x: 0, y: 3}).unwrap(), 0);
+	assert_eq!(bmp.threshold(ThresholdMethod::Fixed(100)).unwrap(), binary);
+	assert_eq!(bmp.threshold(ThresholdMethod::Fixed(250)).unwrap().get_pixel_index(&Point {x: 9, y: 0}).unwrap(), 0);
+
+	let mut counts = [0; 256];
+	counts[40] = 30;
+	counts[191] = 10;
+	let level = otsu_level(&counts);
+	assert!((40..191).contains(&level));
+}
+
+#[test]
+fn test_adaptive_threshold() {
+	// Text-like dark dots on a background that brightens from left to right
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(32, 8, 24);
+	for x in 0..32 {
+		let value = 60 + x as u8 * 6;
+		bmp.draw_line(&Point {x, y: 0}, &Point {x, y: 7}, &Rgb {r: value, g: value, b: value});
+	}
+	for x in [4, 28] {
+		let value = 30 + x as u8 * 6;
+		bmp.draw_point(&Point {x, y: 4}, &Rgb {r: value, g: value, b: value});
+	}
+
+	// A global level cannot find both dots, local levels can
+	for method in [ThresholdMethod::AdaptiveMean {radius: 3, offset: 5.0}, ThresholdMethod::AdaptiveGaussian {sigma: 2.0, offset: 5.0}] {
+		let binary = bmp.threshold(method).unwrap();
+		assert_eq!(binary.get_pixel_index(&Point {x: 4, y: 4}).unwrap(), 0);
+		assert_eq!(binary.get_pixel_index(&Point {x: 28, y: 4}).unwrap(), 0);
+		assert_eq!(binary.get_pixel_index(&Point {x: 28, y: 1}).unwrap(), 1);
+		assert_eq!(binary.get_pixel_index(&Point {x: 4, y: 1}).unwrap(), 1);
+	}
+	assert!(bmp.threshold(ThresholdMethod::AdaptiveGaussian {sigma: 0.0, offset: 0.0}).is_err());
+}
@@ -0,0 +1,163 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Point;
+use crate::Get;
+use crate::resample::*;
+use crate::indexed::*;
+
+pub trait Transform {
+	fn flip_horizontal(&self) -> Vec<u8>;
+	fn flip_vertical(&self) -> Vec<u8>;
+	fn rotate_90(&self) -> Vec<u8>;
+	fn rotate_180(&self) -> Vec<u8>;
+	fn rotate_270(&self) -> Vec<u8>;
+	fn rotate(&self, degrees: f64, background: &Rgb) -> Result<Vec<u8>, String>;
+}
+
+// Builds a width x height bitmap of the same depth, where each pixel is copied from the source pixel chosen by source_of.
+// Indexed images keep their palette and have their color indices copied as they are.
+fn remap<F: Fn(u32, u32) -> Point>(bmp: &Vec<u8>, width: u32, height: u32, source_of: F) -> Vec<u8> {
+	let mut result = new_bitmap_like(bmp, width, height);
+	let indexed = bmp.is_indexed();
+	for y in 0..height {
+		for x in 0..width {
+			if indexed {
+				if let Ok(index) = bmp.get_pixel_index(&source_of(x, y)) {
+					result.set_pixel_index(&Point { x, y }, index);
+				}
+			} else if let Ok(pixel) = bmp.get_pixel(&source_of(x, y)) {
+				result.draw_point(&Point { x, y }, &pixel);
+			}
+		}
+	}
+	result
+}
+
+// Samples the pixel grid at (x, y), where pixel (i, j) is centered on (i + 0.5, j + 0.5).
+// Taps outside the image take the background color, so edges blend smoothly into it.
+pub(crate) fn sample_bilinear(pixels: &[[f64; 3]], width: u32, height: u32, x: f64, y: f64, background: [f64; 3]) -> [f64; 3] {
+	let fx = x - 0.5;
+	let fy = y - 0.5;
+	let x0 = fx.floor();
+	let y0 = fy.floor();
+	let tx = fx - x0;
+	let ty = fy - y0;
+	let tap = |i: f64, j: f64| -> [f64; 3] {
+		if i < 0.0 || j < 0.0 || i >= width as f64 || j >= height as f64 {
+			return background;
+		}
+		pixels[j as usize * width as usize + i as usize]
+	};
+	let p00 = tap(x0, y0);
+	let p10 = tap(x0 + 1.0, y0);
+	let p01 = tap(x0, y0 + 1.0);
+	let p11 = tap(x0 + 1.0, y0 + 1.0);
+	let mut result = [0.0; 3];
+	for channel in 0..3 {
+		let bottom = p00[channel] + (p10[channel] - p00[channel]) * tx;
+		let top = p01[channel] + (p11[channel] - p01[channel]) * tx;
+		result[channel] = bottom + (top - bottom) * ty;
+	}
+	result
+}
+
+impl Transform for Vec<u8> {
+	// Mirrors the image left to right
+	fn flip_horizontal(&self) -> Vec<u8> {
+		let width = self.get_width();
+		remap(self, width, self.get_height(), |x, y| Point { x: width - 1 - x, y })
+	}
+
+	// Mirrors the image top to bottom
+	fn flip_vertical(&self) -> Vec<u8> {
+		let height = self.get_height();
+		remap(self, self.get_width(), height, |x, y| Point { x, y: height - 1 - y })
+	}
+
+	// Quarter turn counterclockwise; width and height swap
+	fn rotate_90(&self) -> Vec<u8> {
+		let height = self.get_height();
+		remap(self, height, self.get_width(), |x, y| Point { x: y, y: height - 1 - x })
+	}
+
+	fn rotate_180(&self) -> Vec<u8> {
+		let width = self.get_width();
+		let height = self.get_height();
+		remap(self, width, height, |x, y| Point { x: width - 1 - x, y: height - 1 - y })
+	}
+
+	// Quarter turn clockwise; width and height swap
+	fn rotate_270(&self) -> Vec<u8> {
+		let width = self.get_width();
+		remap(self, self.get_height(), width, |x, y| Point { x: width - 1 - y, y: x })
+	}
+
+	// Rotates counterclockwise by any angle using bilinear sampling.
+	// The result grows to fit the whole rotated image and uncovered corners are filled with background.
+	fn rotate(&self, degrees: f64, background: &Rgb) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let width = self.get_width();
+		let height = self.get_height();
+		let source = read_pixels(self)?;
+
+		let (sin, cos) = degrees.to_radians().sin_cos();
+		// Round away tiny floating point errors so exact quarter turns do not grow by a pixel
+		let new_width = ((width as f64 * cos.abs() + height as f64 * sin.abs()) - 1e-6).ceil().max(1.0) as u32;
+		let new_height = ((width as f64 * sin.abs() + height as f64 * cos.abs()) - 1e-6).ceil().max(1.0) as u32;
+
+		let center_x = width as f64 / 2.0;
+		let center_y = height as f64 / 2.0;
+		let new_center_x = new_width as f64 / 2.0;
+		let new_center_y = new_height as f64 / 2.0;
+		let fill = [background.r as f64, background.g as f64, background.b as f64];
+
+		let mut output = Vec::with_capacity(new_width as usize * new_height as usize);
+		for y in 0..new_height {
+			for x in 0..new_width {
+				// Rotate the destination pixel center back into the source
+				let dx = x as f64 + 0.5 - new_center_x;
+				let dy = y as f64 + 0.5 - new_center_y;
+				let source_x = dx * cos + dy * sin + center_x;
+				let source_y = -dx * sin + dy * cos + center_y;
+				output.push(sample_bilinear(&source, width, height, source_x, source_y, fill));
+			}
+		}
+		Ok(write_pixels(&output, new_width, new_height, self))
+	}
+}
+
+#[test]
+fn test_quarter_turns_and_flips() {
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(3, 2, 24);
+	bmp.draw_point(&Point {x: 2, y: 0}, &red);
+
+	let turned = bmp.rotate_90();
+	assert_eq!((turned.get_width(), turned.get_height()), (2, 3));
+	assert_eq!(turned.get_pixel(&Point {x: 1, y: 2}).unwrap(), red);
+	assert_eq!(turned.rotate_270(), bmp);
+	assert_eq!(bmp.rotate_180().get_pixel(&Point {x: 0, y: 1}).unwrap(), red);
+	assert_eq!(bmp.rotate_90().rotate_90(), bmp.rotate_180());
+	assert_eq!(bmp.flip_horizontal().get_pixel(&Point {x: 0, y: 0}).unwrap(), red);
+	assert_eq!(bmp.flip_vertical().get_pixel(&Point {x: 2, y: 1}).unwrap(), red);
+	assert_eq!(bmp.flip_horizontal().flip_vertical(), bmp.rotate_180());
+}
+
+#[test]
+fn test_arbitrary_rotation() {
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(10, 10, 24);
+	bmp.draw_circle(&Point {x: 5, y: 5}, 20, &red);
+
+	let rotated = bmp.rotate(45.0, &white).unwrap();
+	assert_eq!(rotated.get_width(), 15);
+	assert_eq!(rotated.get_height(), 15);
+	assert_eq!(rotated.get_pixel(&Point {x: 7, y: 7}).unwrap(), red);
+	assert_eq!(rotated.get_pixel(&Point {x: 0, y: 0}).unwrap(), white);
+
+	// A quarter turn through the general path matches the lossless one
+	assert_eq!(bmp.rotate(90.0, &white).unwrap(), bmp.rotate_90());
+}
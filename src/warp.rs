@@ -0,0 +1,191 @@
+use crate::Bitmap;
+use crate::Rgb;
+use crate::Get;
+use crate::signed::FloatPoint;
+use crate::resample::*;
+use crate::transform::sample_bilinear;
+
+// How source pixels are sampled at fractional positions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+	Nearest,
+	Bilinear,
+	// Catmull-Rom cubic over a 4x4 neighbourhood
+	Bicubic,
+}
+
+// Catmull-Rom weights for the four taps around a fractional offset t in 0..1
+fn catmull_rom_weights(t: f64) -> [f64; 4] {
+	let t2 = t * t;
+	let t3 = t2 * t;
+	[
+		(-t3 + 2.0 * t2 - t) / 2.0,
+		(3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+		(-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+		(t3 - t2) / 2.0,
+	]
+}
+
+// Samples the pixel grid at (x, y), where pixel (i, j) is centered on (i + 0.5, j + 0.5). Positions outside take the background.
+pub(crate) fn sample(pixels: &[[f64; 3]], width: u32, height: u32, x: f64, y: f64, interpolation: Interpolation, background: [f64; 3]) -> [f64; 3] {
+	let tap = |i: f64, j: f64| -> [f64; 3] {
+		if i < 0.0 || j < 0.0 || i >= width as f64 || j >= height as f64 {
+			return background;
+		}
+		pixels[j as usize * width as usize + i as usize]
+	};
+	match interpolation {
+		Interpolation::Nearest => tap(x.floor(), y.floor()),
+		Interpolation::Bilinear => sample_bilinear(pixels, width, height, x, y, background),
+		Interpolation::Bicubic => {
+			let fx = x - 0.5;
+			let fy = y - 0.5;
+			let x0 = fx.floor();
+			let y0 = fy.floor();
+			let weights_x = catmull_rom_weights(fx - x0);
+			let weights_y = catmull_rom_weights(fy - y0);
+			let mut result = [0.0; 3];
+			for (j, weight_y) in weights_y.iter().enumerate() {
+				for (i, weight_x) in weights_x.iter().enumerate() {
+					let pixel = tap(x0 + i as f64 - 1.0, y0 + j as f64 - 1.0);
+					for channel in 0..3 {
+						result[channel] += pixel[channel] * weight_x * weight_y;
+					}
+				}
+			}
+			result
+		}
+	}
+}
+
+// Inverts a 3x3 matrix, failing when it is singular
+fn invert_3x3(m: &[[f64; 3]; 3]) -> Result<[[f64; 3]; 3], String> {
+	let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+	let adjugate = [
+		[cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+		[-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+		[cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+	];
+	let determinant = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+	if determinant.abs() < 1e-12 {
+		return Err("Transform matrix is not invertible".to_string());
+	}
+	let mut inverse = [[0.0; 3]; 3];
+	for row in 0..3 {
+		for column in 0..3 {
+			inverse[row][column] = adjugate[row][column] / determinant;
+		}
+	}
+	Ok(inverse)
+}
+
+// Computes the homography that maps each of the four from points onto the matching to point.
+// For de-skewing, pass the corners of the photographed page as from and the corners of the output rectangle as to.
+pub fn perspective_transform(from: &[FloatPoint; 4], to: &[FloatPoint; 4]) -> Result<[[f64; 3]; 3], String> {
+	// Eight equations in the eight unknowns h11..h32, with h33 fixed at 1
+	let mut system = [[0.0; 9]; 8];
+	for i in 0..4 {
+		let (x, y) = (from[i].x, from[i].y);
+		let (u, v) = (to[i].x, to[i].y);
+		system[2 * i] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+		system[2 * i + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+	}
+
+	// Gaussian elimination with partial pivoting
+	for column in 0..8 {
+		let pivot = (column..8).max_by(|&a, &b| system[a][column].abs().partial_cmp(&system[b][column].abs()).unwrap_or(std::cmp::Ordering::Equal)).unwrap_or(column);
+		if system[pivot][column].abs() < 1e-12 {
+			return Err("Points are degenerate, three of them may be on one line".to_string());
+		}
+		system.swap(column, pivot);
+		let pivot_row = system[column];
+		for (row, equation) in system.iter_mut().enumerate() {
+			if row != column {
+				let factor = equation[column] / pivot_row[column];
+				for k in column..9 {
+					equation[k] -= factor * pivot_row[k];
+				}
+			}
+		}
+	}
+	let h: Vec<f64> = (0..8).map(|i| system[i][8] / system[i][i]).collect();
+	Ok([[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], 1.0]])
+}
+
+pub trait Warp {
+	fn warp_affine(&self, matrix: &[[f64; 3]; 2], width: u32, height: u32, interpolation: Interpolation, background: &Rgb) -> Result<Vec<u8>, String>;
+	fn warp_perspective(&self, matrix: &[[f64; 3]; 3], width: u32, height: u32, interpolation: Interpolation, background: &Rgb) -> Result<Vec<u8>, String>;
+}
+
+impl Warp for Vec<u8> {
+	// Maps the image through the affine matrix [[a, b, tx], [c, d, ty]] (source to destination) into a new width x height bitmap
+	fn warp_affine(&self, matrix: &[[f64; 3]; 2], width: u32, height: u32, interpolation: Interpolation, background: &Rgb) -> Result<Vec<u8>, String> {
+		let full = [matrix[0], matrix[1], [0.0, 0.0, 1.0]];
+		self.warp_perspective(&full, width, height, interpolation, background)
+	}
+
+	// Maps the image through the homography matrix (source to destination) into a new width x height bitmap.
+	// Every destination pixel center is mapped back through the inverse into the source and sampled there.
+	fn warp_perspective(&self, matrix: &[[f64; 3]; 3], width: u32, height: u32, interpolation: Interpolation, background: &Rgb) -> Result<Vec<u8>, String> {
+		if !self.has_file_signature() {
+			return Err("Invalid file signature. Not a Bitmap.".to_string());
+		}
+		let inverse = invert_3x3(matrix)?;
+		let source_width = self.get_width();
+		let source_height = self.get_height();
+		let source = read_pixels(self)?;
+		let fill = [background.r as f64, background.g as f64, background.b as f64];
+
+		let mut output = Vec::with_capacity(width as usize * height as usize);
+		for y in 0..height {
+			for x in 0..width {
+				let dx = x as f64 + 0.5;
+				let dy = y as f64 + 0.5;
+				let w = inverse[2][0] * dx + inverse[2][1] * dy + inverse[2][2];
+				if w.abs() < 1e-12 {
+					output.push(fill);
+					continue;
+				}
+				let source_x = (inverse[0][0] * dx + inverse[0][1] * dy + inverse[0][2]) / w;
+				let source_y = (inverse[1][0] * dx + inverse[1][1] * dy + inverse[1][2]) / w;
+				output.push(sample(&source, source_width, source_height, source_x, source_y, interpolation, fill));
+			}
+		}
+		Ok(write_pixels(&output, width, height, self))
+	}
+}
+
+#[test]
+fn test_warp_affine_translation() {
+	use crate::Point;
+	let red = Rgb {r: 255, g: 0, b: 0};
+	let white = Rgb {r: 255, g: 255, b: 255};
+	let mut bmp:Vec<u8> = Vec::<u8>::new_bitmap(6, 6, 24);
+	bmp.draw_point(&Point {x: 1, y: 1}, &red);
+
+	let moved = bmp.warp_affine(&[[1.0, 0.0, 2.0], [0.0, 1.0, 3.0]], 6, 6, Interpolation::Bicubic, &white).unwrap();
+	assert_eq!(moved.get_pixel(&Point {x: 3, y: 4}).unwrap(), red);
+	assert_eq!(moved.get_pixel(&Point {x: 0, y: 0}).unwrap(), white);
+	assert_eq!(moved.get_pixel(&Point {x: 2, y: 3}).unwrap(), Rgb {r: 0, g: 0, b: 0});
+
+	assert!(bmp.warp_affine(&[[0.0, 0.0, 0.0], [0.0, 0.0, 0.0]], 6, 6, Interpolation::Nearest, &white).is_err());
+}
+
+#[test]
+fn test_perspective_transform() {
+	let quad = [FloatPoint {x: 10.0, y: 5.0}, FloatPoint {x: 90.0, y: 15.0}, FloatPoint {x: 80.0, y: 70.0}, FloatPoint {x: 5.0, y: 60.0}];
+	let rectangle = [FloatPoint {x: 0.0, y: 0.0}, FloatPoint {x: 50.0, y: 0.0}, FloatPoint {x: 50.0, y: 40.0}, FloatPoint {x: 0.0, y: 40.0}];
+	let h = perspective_transform(&quad, &rectangle).unwrap();
+	for i in 0..4 {
+		let (x, y) = (quad[i].x, quad[i].y);
+		let w = h[2][0] * x + h[2][1] * y + h[2][2];
+		let u = (h[0][0] * x + h[0][1] * y + h[0][2]) / w;
+		let v = (h[1][0] * x + h[1][1] * y + h[1][2]) / w;
+		assert!((u - rectangle[i].x).abs() < 1e-6 && (v - rectangle[i].y).abs() < 1e-6);
+	}
+
+	// The de-skewed page has the requested size
+	let page:Vec<u8> = Vec::<u8>::new_bitmap(100, 80, 24);
+	let deskewed = page.warp_perspective(&h, 50, 40, Interpolation::Bilinear, &Rgb {r: 0, g: 0, b: 0}).unwrap();
+	assert_eq!((deskewed.get_width(), deskewed.get_height()), (50, 40));
+}